@@ -44,13 +44,12 @@ print("""// generated by misc/make_tz_map.py
 
 use maplit::hashmap;
 
-#[allow(dead_code)]
-pub fn timezone_id_by_name(s: &str) -> u16 {
+pub fn timezone_id_by_name(s: &str) -> Option<u16> {
     let map = hashmap! {""")
 for k, v in timezone_id_by_name.items():
     print('        "{}" => {},'.format(k, v))
 print("""    };
-    map[s]
+    map.get(s).copied()
 }""")
 
 print("""
@@ -0,0 +1,74 @@
+// MIT License
+//
+// Copyright (c) 2021 Hajime Nakagami<nakagami@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use super::connection::Connection;
+use super::error::Error;
+use std::io;
+
+// std::io::Read over a blob, one op_get_segment at a time
+pub struct BlobReader<'conn> {
+    conn: &'conn Connection,
+    blob_handle: i32,
+    buf: Vec<u8>,
+    pos: usize,
+    eof: bool,
+}
+
+impl BlobReader<'_> {
+    pub(crate) fn new(conn: &Connection, blob_handle: i32) -> BlobReader<'_> {
+        BlobReader {
+            conn,
+            blob_handle,
+            buf: Vec::new(),
+            pos: 0,
+            eof: false,
+        }
+    }
+}
+
+impl io::Read for BlobReader<'_> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        // a request may return no bytes before the end, ask again
+        while self.pos == self.buf.len() && !self.eof {
+            let (data, eof) =
+                self.conn
+                    ._read_blob_segment(self.blob_handle)
+                    .map_err(|e| match e {
+                        Error::IoError(e) => e,
+                        e => io::Error::other(format!("{:?}", e)),
+                    })?;
+            self.buf = data;
+            self.pos = 0;
+            self.eof = eof;
+        }
+        let n = out.len().min(self.buf.len() - self.pos);
+        out[..n].copy_from_slice(&self.buf[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+impl Drop for BlobReader<'_> {
+    fn drop(&mut self) {
+        let _ = self.conn._close_blob(self.blob_handle);
+    }
+}
@@ -0,0 +1,82 @@
+// MIT License
+//
+// Copyright (c) 2021 Hajime Nakagami<nakagami@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use super::connection_async::ConnectionAsync;
+use super::error::Error;
+
+macro_rules! debug_print {
+    //    ($( $args:expr ),*) => { println!( $( $args ),* ); }
+    ($( $args:expr ),*) => {};
+}
+
+// async counterpart of BlobReader, one op_get_segment at a time
+pub struct BlobReaderAsync<'conn> {
+    conn: &'conn ConnectionAsync,
+    blob_handle: i32,
+    buf: Vec<u8>,
+    pos: usize,
+    eof: bool,
+    closed: bool,
+}
+
+impl BlobReaderAsync<'_> {
+    pub(crate) fn new(conn: &ConnectionAsync, blob_handle: i32) -> BlobReaderAsync<'_> {
+        BlobReaderAsync {
+            conn,
+            blob_handle,
+            buf: Vec::new(),
+            pos: 0,
+            eof: false,
+            closed: false,
+        }
+    }
+
+    // 0 at the end of the blob, like std::io::Read::read()
+    pub async fn read(&mut self, out: &mut [u8]) -> Result<usize, Error> {
+        // a request may return no bytes before the end, ask again
+        while self.pos == self.buf.len() && !self.eof {
+            let (data, eof) = self.conn._read_blob_segment(self.blob_handle).await?;
+            self.buf = data;
+            self.pos = 0;
+            self.eof = eof;
+        }
+        let n = out.len().min(self.buf.len() - self.pos);
+        out[..n].copy_from_slice(&self.buf[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+
+    // release the blob handle on the server
+    pub async fn close(mut self) -> Result<(), Error> {
+        self.closed = true;
+        self.conn._close_blob(self.blob_handle).await
+    }
+}
+
+impl Drop for BlobReaderAsync<'_> {
+    fn drop(&mut self) {
+        // see Drop for WireProtocolAsync, the blob is released with the transaction
+        if !self.closed {
+            debug_print!("BlobReaderAsync dropped without close()");
+        }
+    }
+}
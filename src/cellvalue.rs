@@ -21,11 +21,31 @@
 // SOFTWARE.
 
 use super::error::{Error, ValueError};
+use super::utils;
 use chrono;
 use chrono_tz;
 use rust_decimal;
+use std::fmt;
 use std::result::Result;
 
+// 8 bytes blob id (quad) given by the server
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Hash)]
+pub struct BlobId(pub [u8; 8]);
+
+impl BlobId {
+    pub(crate) fn from_slice(b: &[u8]) -> Result<BlobId, ValueError> {
+        b.try_into()
+            .map(BlobId)
+            .map_err(|_| ValueError::new(&format!("blob id of {} bytes", b.len())))
+    }
+}
+
+impl fmt::Display for BlobId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", hex::encode(self.0))
+    }
+}
+
 #[derive(PartialEq, Debug, Clone)]
 pub enum CellValue {
     Null,
@@ -46,6 +66,53 @@ pub enum CellValue {
     TimeTz((chrono::NaiveTime, chrono_tz::Tz)),
     Decimal(rust_decimal::Decimal),
     Boolean(bool),
+    BlobId(BlobId), // blob not read, see Statement::set_fetch_blobs() and set_blob_inline_threshold()
+    Octets(Vec<u8>), // CHAR/VARCHAR CHARACTER SET OCTETS
+    Scaled(i128, i32), // mantissa * 10^scale, NUMERIC(38) values too large for Decimal
+}
+
+impl CellValue {
+    // == with a tolerance for FLOAT and DOUBLE PRECISION values
+    pub fn approx_eq(&self, other: &CellValue, epsilon: f64) -> bool {
+        let as_f64 = |v: &CellValue| match v {
+            CellValue::Float(f) => Some(*f as f64),
+            CellValue::Double(f) => Some(*f),
+            _ => None,
+        };
+        match (as_f64(self), as_f64(other)) {
+            (Some(a), Some(b)) => (a - b).abs() <= epsilon,
+            _ => self == other,
+        }
+    }
+}
+
+// binary blobs are written in hex
+impl fmt::Display for CellValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CellValue::Null => write!(f, "NULL"),
+            CellValue::Text(v) => write!(f, "{}", v),
+            CellValue::Varying(v) => write!(f, "{}", v),
+            CellValue::Short(v) => write!(f, "{}", v),
+            CellValue::Long(v) => write!(f, "{}", v),
+            CellValue::Float(v) => write!(f, "{}", v),
+            CellValue::Time(v) => write!(f, "{}", v),
+            CellValue::Date(v) => write!(f, "{}", v),
+            CellValue::Double(v) => write!(f, "{}", v),
+            CellValue::TimeStamp(v) => write!(f, "{}", v),
+            CellValue::BlobBinary(v) => write!(f, "{}", hex::encode(v)),
+            CellValue::BlobText(v) => write!(f, "{}", String::from_utf8_lossy(v)),
+            CellValue::Int64(v) => write!(f, "{}", v),
+            CellValue::Int128(v) => write!(f, "{}", v),
+            CellValue::TimeStampTz(v) => write!(f, "{}", v),
+            CellValue::TimeTz((t, tz)) => write!(f, "{} {}", t, tz),
+            CellValue::Decimal(v) => write!(f, "{}", v),
+            CellValue::Boolean(v) => write!(f, "{}", v),
+            CellValue::BlobId(v) => write!(f, "blob {}", v),
+            CellValue::Octets(v) => write!(f, "{}", hex::encode(v)),
+            CellValue::Scaled(n, scale) => write!(f, "{}", utils::scaled_to_string(*n, *scale)),
+        }
+    }
 }
 
 pub trait CellValueToVal<T> {
@@ -66,6 +133,13 @@ where
     }
 }
 
+// the cell as is, e.g. to tell a CellValue::BlobId from the content
+impl CellValueToVal<CellValue> for CellValue {
+    fn to_val(self) -> Result<CellValue, Error> {
+        Ok(self)
+    }
+}
+
 impl CellValueToVal<String> for CellValue {
     fn to_val(self) -> Result<String, Error> {
         match self {
@@ -73,6 +147,8 @@ impl CellValueToVal<String> for CellValue {
             CellValue::Varying(v) => Ok(v.to_string()),
             CellValue::BlobBinary(v) => Ok(String::from_utf8_lossy(&v).to_string()),
             CellValue::BlobText(v) => Ok(String::from_utf8_lossy(&v).to_string()),
+            CellValue::Octets(v) => String::from_utf8(v)
+                .map_err(|_| Error::ValueError(ValueError::new("Can't convert string"))),
             _ => Err(Error::ValueError(ValueError::new("Can't convert string"))),
         }
     }
@@ -84,6 +160,29 @@ impl CellValueToVal<i64> for CellValue {
             CellValue::Short(v) => Ok(v.into()),
             CellValue::Long(v) => Ok(v.into()),
             CellValue::Int64(v) => Ok(v.into()),
+            // NUMERIC(n, 0) etc.
+            CellValue::Decimal(v) if v.fract().is_zero() => i64::try_from(v)
+                .map_err(|_| Error::ValueError(ValueError::new("Can't convert int"))),
+            CellValue::Decimal(_) => Err(Error::ValueError(ValueError::new(
+                "Can't convert decimal with fractional part to int",
+            ))),
+            _ => Err(Error::ValueError(ValueError::new("Can't convert int"))),
+        }
+    }
+}
+
+impl CellValueToVal<i128> for CellValue {
+    fn to_val(self) -> Result<i128, Error> {
+        match self {
+            CellValue::Short(v) => Ok(v.into()),
+            CellValue::Long(v) => Ok(v.into()),
+            CellValue::Int64(v) => Ok(v.into()),
+            CellValue::Int128(v) => Ok(v),
+            CellValue::Decimal(v) if v.fract().is_zero() => i128::try_from(v)
+                .map_err(|_| Error::ValueError(ValueError::new("Can't convert int"))),
+            CellValue::Decimal(_) => Err(Error::ValueError(ValueError::new(
+                "Can't convert decimal with fractional part to int",
+            ))),
             _ => Err(Error::ValueError(ValueError::new("Can't convert int"))),
         }
     }
@@ -124,15 +223,29 @@ impl CellValueToVal<Vec<u8>> for CellValue {
         match self {
             CellValue::BlobBinary(v) => Ok(v.clone()),
             CellValue::BlobText(v) => Ok(v.clone()),
+            CellValue::Octets(v) => Ok(v),
             _ => Err(Error::ValueError(ValueError::new("Can't convert binary"))),
         }
     }
 }
 
+impl CellValueToVal<BlobId> for CellValue {
+    fn to_val(self) -> Result<BlobId, Error> {
+        match self {
+            CellValue::BlobId(v) => Ok(v),
+            _ => Err(Error::ValueError(ValueError::new("Can't convert blob id"))),
+        }
+    }
+}
+
 impl CellValueToVal<bool> for CellValue {
     fn to_val(self) -> Result<bool, Error> {
         match self {
             CellValue::Boolean(v) => Ok(v),
+            // integer flag columns of schemas without native BOOLEAN
+            CellValue::Short(v) => Ok(v != 0),
+            CellValue::Long(v) => Ok(v != 0),
+            CellValue::Int64(v) => Ok(v != 0),
             _ => Err(Error::ValueError(ValueError::new("Can't convert bool"))),
         }
     }
@@ -176,6 +289,26 @@ impl CellValueToVal<rust_decimal::Decimal> for CellValue {
     }
 }
 
+#[cfg(feature = "bigdecimal")]
+impl CellValueToVal<bigdecimal::BigDecimal> for CellValue {
+    fn to_val(self) -> Result<bigdecimal::BigDecimal, Error> {
+        use bigdecimal::num_bigint::BigInt;
+        use bigdecimal::BigDecimal;
+        match self {
+            CellValue::Short(v) => Ok(BigDecimal::from(v)),
+            CellValue::Long(v) => Ok(BigDecimal::from(v)),
+            CellValue::Int64(v) => Ok(BigDecimal::from(v)),
+            CellValue::Int128(v) => Ok(BigDecimal::from(v)),
+            CellValue::Decimal(v) => Ok(BigDecimal::new(
+                BigInt::from(v.mantissa()),
+                v.scale() as i64,
+            )),
+            CellValue::Scaled(n, scale) => Ok(BigDecimal::new(BigInt::from(n), -scale as i64)),
+            _ => Err(Error::ValueError(ValueError::new("Can't convert decimal"))),
+        }
+    }
+}
+
 impl CellValueToVal<(chrono::NaiveTime, chrono_tz::Tz)> for CellValue {
     fn to_val(self) -> Result<(chrono::NaiveTime, chrono_tz::Tz), Error> {
         match self {
@@ -197,3 +330,112 @@ impl CellValueToVal<chrono::DateTime<chrono_tz::Tz>> for CellValue {
         }
     }
 }
+
+#[test]
+fn test_decimal_to_int() {
+    use rust_decimal_macros::dec;
+
+    let v: i64 = CellValue::Decimal(dec!(12345)).to_val().unwrap();
+    assert_eq!(v, 12345);
+    let v: i32 = CellValue::Decimal(dec!(-12.000)).to_val().unwrap();
+    assert_eq!(v, -12);
+    let v: i128 = CellValue::Decimal(dec!(12345)).to_val().unwrap();
+    assert_eq!(v, 12345);
+
+    let r: Result<i64, Error> = CellValue::Decimal(dec!(1.5)).to_val();
+    assert!(r.is_err());
+}
+
+#[test]
+fn test_approx_eq() {
+    assert_ne!(CellValue::Double(0.1 + 0.2), CellValue::Double(0.3));
+    assert!(CellValue::Double(0.1 + 0.2).approx_eq(&CellValue::Double(0.3), 1e-9));
+    assert!(CellValue::Float(0.1).approx_eq(&CellValue::Double(0.1), 1e-6));
+    assert!(!CellValue::Float(0.1).approx_eq(&CellValue::Float(0.2), 1e-6));
+
+    assert!(CellValue::Long(1).approx_eq(&CellValue::Long(1), 1e-6));
+    assert!(!CellValue::Long(1).approx_eq(&CellValue::Double(1.0), 1e-6));
+    assert!(CellValue::Null.approx_eq(&CellValue::Null, 1e-6));
+}
+
+#[test]
+fn test_display() {
+    use rust_decimal_macros::dec;
+
+    assert_eq!(CellValue::Null.to_string(), "NULL");
+    assert_eq!(CellValue::Varying("foo".to_string()).to_string(), "foo");
+    assert_eq!(CellValue::Long(-1).to_string(), "-1");
+    assert_eq!(CellValue::Decimal(dec!(-0.123)).to_string(), "-0.123");
+    assert_eq!(
+        CellValue::Date(chrono::NaiveDate::from_ymd_opt(1967, 8, 11).unwrap()).to_string(),
+        "1967-08-11"
+    );
+    assert_eq!(CellValue::BlobBinary(vec![0, 255]).to_string(), "00ff");
+    assert_eq!(CellValue::BlobText(b"pen".to_vec()).to_string(), "pen");
+}
+
+#[test]
+fn test_int_to_bool() {
+    let v: bool = CellValue::Boolean(true).to_val().unwrap();
+    assert!(v);
+    let v: bool = CellValue::Short(0).to_val().unwrap();
+    assert!(!v);
+    let v: bool = CellValue::Short(1).to_val().unwrap();
+    assert!(v);
+    let v: bool = CellValue::Long(-1).to_val().unwrap();
+    assert!(v);
+    let v: bool = CellValue::Int64(0).to_val().unwrap();
+    assert!(!v);
+    let r: Result<bool, Error> = CellValue::Text("1".to_string()).to_val();
+    assert!(r.is_err());
+}
+
+#[test]
+fn test_blob_id() {
+    let id = BlobId([0, 1, 0, 0, 0, 0, 0, 2]);
+    let r: Result<Vec<u8>, Error> = CellValue::BlobId(id).to_val();
+    assert!(r.is_err());
+    let v: CellValue = CellValue::BlobId(id).to_val().unwrap();
+    assert_eq!(v, CellValue::BlobId(id));
+    assert_eq!(v.to_string(), "blob 0001000000000002");
+    let v: BlobId = CellValue::BlobId(id).to_val().unwrap();
+    assert_eq!(v, id);
+    let r: Result<BlobId, Error> = CellValue::BlobBinary(vec![0; 8]).to_val();
+    assert!(r.is_err());
+
+    assert_eq!(BlobId::from_slice(&[0, 1, 0, 0, 0, 0, 0, 2]).unwrap(), id);
+    assert!(BlobId::from_slice(&[0, 1]).is_err());
+}
+
+#[test]
+fn test_octets() {
+    let v: Vec<u8> = CellValue::Octets(vec![0, 0xff]).to_val().unwrap();
+    assert_eq!(v, vec![0, 0xff]);
+    assert_eq!(CellValue::Octets(vec![0, 0xff]).to_string(), "00ff");
+    let s: String = CellValue::Octets(b"abc".to_vec()).to_val().unwrap();
+    assert_eq!(s, "abc");
+    let r: Result<String, Error> = CellValue::Octets(vec![0, 0xff]).to_val();
+    assert!(r.is_err());
+}
+
+#[test]
+fn test_scaled_display() {
+    assert_eq!(CellValue::Scaled(-12345, -2).to_string(), "-123.45");
+}
+
+#[cfg(feature = "bigdecimal")]
+#[test]
+fn test_bigdecimal_value() {
+    use rust_decimal_macros::dec;
+    use std::str::FromStr;
+    let n = 12345678901234567890123456789012345678;
+    let v: bigdecimal::BigDecimal = CellValue::Scaled(n, -4).to_val().unwrap();
+    assert_eq!(
+        v,
+        bigdecimal::BigDecimal::from_str("1234567890123456789012345678901234.5678").unwrap()
+    );
+    let v: bigdecimal::BigDecimal = CellValue::Decimal(dec!(-0.123)).to_val().unwrap();
+    assert_eq!(v, bigdecimal::BigDecimal::from_str("-0.123").unwrap());
+    let v: bigdecimal::BigDecimal = CellValue::Int128(i128::MAX).to_val().unwrap();
+    assert_eq!(v, bigdecimal::BigDecimal::from(i128::MAX));
+}
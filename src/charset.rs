@@ -0,0 +1,84 @@
+// MIT License
+//
+// Copyright (c) 2021 Hajime Nakagami<nakagami@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use super::error::{Error, ValueError};
+
+// Character sets a text blob can be decoded from, see Row::get_blob_string()
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Charset {
+    Utf8,
+    Iso8859_1,
+    Win1252,
+}
+
+// WIN1252 0x80..0x9F, the undefined codes are kept as C1 controls
+const WIN1252_80_9F: [char; 32] = [
+    '\u{20AC}', '\u{0081}', '\u{201A}', '\u{0192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{02C6}', '\u{2030}', '\u{0160}', '\u{2039}', '\u{0152}', '\u{008D}', '\u{017D}', '\u{008F}',
+    '\u{0090}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{02DC}', '\u{2122}', '\u{0161}', '\u{203A}', '\u{0153}', '\u{009D}', '\u{017E}', '\u{0178}',
+];
+
+impl Charset {
+    // Firebird character set name, e.g. "WIN1252"
+    pub fn from_name(name: &str) -> Option<Charset> {
+        match name.trim().to_uppercase().as_str() {
+            "UTF8" | "UNICODE_FSS" => Some(Charset::Utf8),
+            "ISO8859_1" => Some(Charset::Iso8859_1),
+            "WIN1252" => Some(Charset::Win1252),
+            _ => None,
+        }
+    }
+
+    pub fn decode(&self, b: &[u8]) -> Result<String, Error> {
+        match self {
+            Charset::Utf8 => String::from_utf8(b.to_vec())
+                .map_err(|_| Error::ValueError(ValueError::new("invalid UTF8 text"))),
+            Charset::Iso8859_1 => Ok(b.iter().map(|&c| c as char).collect()),
+            Charset::Win1252 => Ok(b
+                .iter()
+                .map(|&c| match c {
+                    0x80..=0x9F => WIN1252_80_9F[(c - 0x80) as usize],
+                    _ => c as char,
+                })
+                .collect()),
+        }
+    }
+}
+
+#[test]
+fn test_charset_decode() {
+    assert_eq!(
+        Charset::Utf8.decode("caf\u{e9}".as_bytes()).unwrap(),
+        "café"
+    );
+    assert!(Charset::Utf8.decode(b"caf\xe9").is_err());
+    assert_eq!(Charset::Iso8859_1.decode(b"caf\xe9").unwrap(), "café");
+    assert_eq!(
+        Charset::Win1252
+            .decode(b"\x80 caf\xe9 \x93q\x94 \x81")
+            .unwrap(),
+        "€ café “q” \u{81}"
+    );
+    assert_eq!(Charset::from_name("win1252"), Some(Charset::Win1252));
+    assert_eq!(Charset::from_name("KOI8R"), None);
+}
@@ -25,21 +25,59 @@
 use std::cell::RefCell;
 use std::collections::HashMap;
 
-use super::cellvalue::CellValue;
-use super::conn_params::ConnParams;
-use super::error::Error;
-use super::params::Params;
+use super::conn_params::{self, ConnParams};
+use super::database_info::DatabaseInfo;
+use super::error::{Error, ValueError};
+use super::event_conduit::EventConduit;
+use super::param::{Param, ToSqlParam};
+use super::params::{sql_in, Params};
+use super::row::{ResultSetMetaData, Row, Rows};
 use super::statement::Statement;
 use super::transaction::*;
+use super::wirechannel::WireChannel;
 use super::wireprotocol::*;
-use super::xsqlvar::XSQLVar;
+use super::xsqlvar::{calc_blr, XSQLVar};
 use super::*;
 
+// trans_handle after the connection transaction ended without a new one started
+pub(crate) const NO_TRANSACTION: i32 = -1;
+
+// whether statements on the connection (not in a transaction()) are committed
+// as they are executed, or wait for Connection::commit()
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum ExecutionMode {
+    AutoCommit,
+    Manual,
+}
+
+// kinds of objects for Connection::object_exists()
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum ObjectKind {
+    Table, // views too
+    Index,
+    Trigger,
+    Generator,
+}
+
+impl ObjectKind {
+    // system table and its name column
+    pub(crate) fn catalog(&self) -> (&'static str, &'static str) {
+        match self {
+            ObjectKind::Table => ("RDB$RELATIONS", "RDB$RELATION_NAME"),
+            ObjectKind::Index => ("RDB$INDICES", "RDB$INDEX_NAME"),
+            ObjectKind::Trigger => ("RDB$TRIGGERS", "RDB$TRIGGER_NAME"),
+            ObjectKind::Generator => ("RDB$GENERATORS", "RDB$GENERATOR_NAME"),
+        }
+    }
+}
+
 pub struct Connection {
-    wp: RefCell<WireProtocol>,
+    pub(crate) wp: RefCell<WireProtocol>,
     trans_handle: i32, // transaction for operating from connection methods
     conn_params: ConnParams,
     conn_options: HashMap<String, String>,
+    identity: Option<(String, String)>, // (CURRENT_USER, CURRENT_ROLE)
+    execution_mode: ExecutionMode,
 }
 
 impl Connection {
@@ -79,6 +117,8 @@ impl Connection {
             trans_handle,
             conn_params,
             conn_options,
+            identity: None,
+            execution_mode: ExecutionMode::AutoCommit,
         })
     }
 
@@ -121,6 +161,8 @@ impl Connection {
             trans_handle,
             conn_params,
             conn_options,
+            identity: None,
+            execution_mode: ExecutionMode::AutoCommit,
         })
     }
 
@@ -129,15 +171,44 @@ impl Connection {
         wp.op_exec_immediate(trans_handle, query)?;
         wp.op_response()?;
 
-        // commit automatically
-        wp.op_commit_retaining(trans_handle)?;
-        wp.op_response()?;
+        if trans_handle == self.trans_handle && utils::ends_transaction(query) {
+            self.trans_handle = NO_TRANSACTION;
+            return Ok(());
+        }
+        // only the connection transaction, a transaction() waits for its commit()
+        if self.is_autocommit(trans_handle) {
+            wp.op_commit_retaining(trans_handle)?;
+            wp.op_response()?;
+        }
 
         Ok(())
     }
 
     pub fn execute_batch(&mut self, query: &str) -> Result<(), Error> {
-        self._execute_batch(query, self.trans_handle)
+        let trans_handle = self.active_trans_handle()?;
+        self._execute_batch(query, trans_handle)
+    }
+
+    // statements of a script (";" separated, SET TERM aware) run one by one
+    // in the connection transaction, committed at the end.
+    // COMMIT and ROLLBACK in the script retain the connection transaction
+    pub fn execute_script(&mut self, script: &str) -> Result<(), Error> {
+        for query in utils::split_sql_script(script) {
+            let words: Vec<String> = query.split_whitespace().map(|w| w.to_uppercase()).collect();
+            if words == ["COMMIT"] || words == ["COMMIT", "WORK"] {
+                self.commit()?;
+                continue;
+            }
+            if words == ["ROLLBACK"] || words == ["ROLLBACK", "WORK"] {
+                self.rollback()?;
+                continue;
+            }
+            let trans_handle = self.active_trans_handle()?;
+            let mut wp = self.wp.borrow_mut();
+            wp.op_exec_immediate(trans_handle, &query)?;
+            wp.op_response()?;
+        }
+        self.commit()
     }
 
     pub(crate) fn _execute<P: Params>(
@@ -145,38 +216,14 @@ impl Connection {
         query: &str,
         params: P,
         trans_handle: i32,
-    ) -> Result<(), Error> {
-        let mut stmt = {
-            let mut wp = self.wp.borrow_mut();
-            wp.op_allocate_statement()?;
-
-            let mut stmt_handle = if wp.accept_type == PTYPE_LAZY_SEND {
-                wp.lazy_response_count += 1;
-                -1
-            } else {
-                let (stmt_handle, _, _) = wp.op_response()?;
-                stmt_handle
-            };
-
-            wp.op_prepare_statement(stmt_handle, trans_handle, query)?;
-            if wp.accept_type == PTYPE_LAZY_SEND && wp.lazy_response_count > 0 {
-                wp.lazy_response_count -= 1;
-                let (h, _, _) = wp.op_response()?;
-                stmt_handle = h;
-            }
-            let (_, buf, _) = wp.op_response()?;
-            let (stmt_type, xsqlda) = wp.parse_xsqlda(&buf, stmt_handle)?;
-
-            Statement::new(self, trans_handle, stmt_handle, stmt_type, xsqlda, true)
-        };
-
-        stmt.execute(params)?;
-
-        Ok(())
+    ) -> Result<usize, Error> {
+        let mut stmt = self._prepare(query, trans_handle)?;
+        stmt.execute(params)
     }
 
-    pub fn execute<P: Params>(&mut self, query: &str, params: P) -> Result<(), Error> {
-        self._execute(query, params, self.trans_handle)
+    // number of inserted, updated or deleted rows
+    pub fn execute<P: Params>(&mut self, query: &str, params: P) -> Result<usize, Error> {
+        self._execute(query, params, self.active_trans_handle()?)
     }
 
     pub(crate) fn _commit(&self, trans_handle: i32) -> Result<(), Error> {
@@ -187,7 +234,7 @@ impl Connection {
     }
 
     pub fn commit(&self) -> Result<(), Error> {
-        self._commit(self.trans_handle)
+        self._commit(self.active_trans_handle()?)
     }
 
     pub(crate) fn _begin_trans(&mut self) -> Result<i32, Error> {
@@ -197,7 +244,37 @@ impl Connection {
         Ok(trans_handle)
     }
 
-    pub(crate) fn _rollback(&mut self, trans_handle: i32) -> Result<(), Error> {
+    pub(crate) fn _transaction_id(&self, trans_handle: i32) -> Result<u64, Error> {
+        let mut wp = self.wp.borrow_mut();
+        let buf = wp.info_request(
+            OP_INFO_TRANSACTION,
+            trans_handle,
+            &[ISC_INFO_TRA_ID as u8, ISC_INFO_END],
+        )?;
+        utils::parse_transaction_id(&buf)
+            .ok_or_else(|| Error::ValueError(ValueError::new("can't parse transaction id")))
+    }
+
+    // server side transaction id, MON$TRANSACTIONS.MON$TRANSACTION_ID
+    pub fn transaction_id(&self) -> Result<u64, Error> {
+        self._transaction_id(self.active_trans_handle()?)
+    }
+
+    // server side attachment id, MON$ATTACHMENTS.MON$ATTACHMENT_ID
+    pub fn attachment_id(&self) -> Result<i64, Error> {
+        let mut wp = self.wp.borrow_mut();
+        let db_handle = wp.db_handle;
+        let buf = wp.info_request(
+            OP_INFO_DATABASE,
+            db_handle,
+            &[ISC_INFO_ATTACHMENT_ID, ISC_INFO_END],
+        )?;
+        utils::parse_attachment_id(&buf)
+            .map(|id| id as i64)
+            .ok_or_else(|| Error::ValueError(ValueError::new("can't parse attachment id")))
+    }
+
+    pub(crate) fn _rollback(&self, trans_handle: i32) -> Result<(), Error> {
         let mut wp = self.wp.borrow_mut();
         wp.op_rollback_retaining(trans_handle)?;
         wp.op_response()?;
@@ -205,7 +282,141 @@ impl Connection {
     }
 
     pub fn rollback(&mut self) -> Result<(), Error> {
-        self._rollback(self.trans_handle)
+        self._rollback(self.active_trans_handle()?)
+    }
+
+    // Firebird 4 or later, clears context variables, role, time zone etc.
+    // for reusing the connection by another unrelated client
+    pub fn reset_session(&mut self) -> Result<(), Error> {
+        self.identity = None;
+        self.execute_batch("ALTER SESSION RESET")
+    }
+
+    // effective user and role after authentication and mapping, queried once
+    fn identity(&mut self) -> Result<(String, String), Error> {
+        if let Some(identity) = &self.identity {
+            return Ok(identity.clone());
+        }
+        let mut stmt = self.prepare("SELECT CURRENT_USER, CURRENT_ROLE FROM RDB$DATABASE")?;
+        let row = stmt.query(())?.next().unwrap();
+        let identity: (String, String) = (row.get(0)?, row.get(1)?);
+        drop(stmt);
+        self.identity = Some(identity.clone());
+        Ok(identity)
+    }
+
+    pub fn current_user(&mut self) -> Result<String, Error> {
+        Ok(self.identity()?.0)
+    }
+
+    // "NONE" without a role
+    pub fn current_role(&mut self) -> Result<String, Error> {
+        Ok(self.identity()?.1)
+    }
+
+    // Firebird 4 or later, the role must be granted to the current user.
+    // "Quoted" names are used as is, others are uppercased
+    pub fn set_role(&mut self, name: &str) -> Result<(), Error> {
+        self.identity = None;
+        self.execute_batch(&format!("SET ROLE {}", utils::quote_identifier(name)))
+    }
+
+    // current values of the generators, in one query. Names are matched like
+    // object_exists(), "quoted" names as is and others case-insensitively
+    pub fn generator_values(&mut self, names: &[&str]) -> Result<Vec<i64>, Error> {
+        if names.is_empty() {
+            return Ok(Vec::new());
+        }
+        let columns: Vec<String> = names
+            .iter()
+            .map(|n| format!("GEN_ID({}, 0)", utils::quote_identifier(n)))
+            .collect();
+        let query = format!("SELECT {} FROM RDB$DATABASE", columns.join(", "));
+        let mut stmt = self.prepare(&query)?;
+        let row = stmt.query(())?.next().unwrap();
+        (0..names.len()).map(|i| row.get(i)).collect()
+    }
+
+    // the connection transaction, until it is ended by e.g. execute_batch("COMMIT")
+    // and reset_transaction() starts a new one
+    fn active_trans_handle(&self) -> Result<i32, Error> {
+        if self.trans_handle == NO_TRANSACTION {
+            Err(Error::NoTransaction)
+        } else {
+            Ok(self.trans_handle)
+        }
+    }
+
+    // statements on the connection transaction commit by the execution mode,
+    // statements in a transaction() wait for its commit()
+    fn is_autocommit(&self, trans_handle: i32) -> bool {
+        trans_handle == self.trans_handle && self.execution_mode == ExecutionMode::AutoCommit
+    }
+
+    pub fn execution_mode(&self) -> ExecutionMode {
+        self.execution_mode
+    }
+
+    // the connection transaction is committed and restarted in the new mode
+    pub fn set_execution_mode(&mut self, mode: ExecutionMode) -> Result<(), Error> {
+        if mode == self.execution_mode {
+            return Ok(());
+        }
+        let trans_handle = self.active_trans_handle()?;
+        let mut wp = self.wp.borrow_mut();
+        wp.op_commit(trans_handle)?;
+        wp.op_response()?;
+        self.trans_handle = NO_TRANSACTION;
+        wp.op_transaction(mode == ExecutionMode::AutoCommit)?;
+        let (trans_handle, _, _) = wp.op_response()?;
+        self.trans_handle = trans_handle;
+        self.execution_mode = mode;
+        Ok(())
+    }
+
+    // roll back the connection transaction and start a new one,
+    // to recover from a transaction left in a bad state by a failed statement
+    pub fn reset_transaction(&mut self) -> Result<(), Error> {
+        if self.trans_handle != NO_TRANSACTION {
+            let mut wp = self.wp.borrow_mut();
+            wp.op_rollback(self.trans_handle)?;
+            // the old transaction may already be gone on the server side
+            if let Err(Error::IoError(e)) = wp.op_response() {
+                return Err(Error::IoError(e));
+            }
+            self.trans_handle = NO_TRANSACTION;
+        }
+        self.restart_transaction()
+    }
+
+    // commit() and rollback() retain the connection transaction, these end it
+    // (releasing its snapshot) and start a new one for the following statements
+    pub fn commit_hard(&mut self) -> Result<(), Error> {
+        let trans_handle = self.active_trans_handle()?;
+        let mut wp = self.wp.borrow_mut();
+        wp.op_commit(trans_handle)?;
+        wp.op_response()?;
+        self.trans_handle = NO_TRANSACTION;
+        drop(wp);
+        self.restart_transaction()
+    }
+
+    pub fn rollback_hard(&mut self) -> Result<(), Error> {
+        let trans_handle = self.active_trans_handle()?;
+        let mut wp = self.wp.borrow_mut();
+        wp.op_rollback(trans_handle)?;
+        wp.op_response()?;
+        self.trans_handle = NO_TRANSACTION;
+        drop(wp);
+        self.restart_transaction()
+    }
+
+    fn restart_transaction(&mut self) -> Result<(), Error> {
+        let mut wp = self.wp.borrow_mut();
+        wp.op_transaction(self.execution_mode == ExecutionMode::AutoCommit)?;
+        let (trans_handle, _, _) = wp.op_response()?;
+        self.trans_handle = trans_handle;
+        Ok(())
     }
 
     pub fn _prepare(&mut self, query: &str, trans_handle: i32) -> Result<Statement, Error> {
@@ -235,18 +446,204 @@ impl Connection {
             stmt_handle,
             stmt_type,
             xsqlda,
-            true, // autocommit is true
+            self.is_autocommit(trans_handle),
         ))
     }
 
     pub fn prepare(&mut self, query: &str) -> Result<Statement, Error> {
-        self._prepare(query, self.trans_handle)
+        self._prepare(query, self.active_trans_handle()?)
+    }
+
+    pub fn query_in(
+        &mut self,
+        prefix: &str,
+        values: &[Param],
+        suffix: &str,
+    ) -> Result<Rows, Error> {
+        let query = format!("{}{}{}", prefix, sql_in(values.len()), suffix);
+        let params: Vec<&dyn ToSqlParam> = values.iter().map(|v| v as &dyn ToSqlParam).collect();
+        let mut stmt = self.prepare(&query)?;
+        stmt.query(params.as_slice())
+    }
+
+    // metadata and all rows, the statement is dropped before returning
+    pub fn query_all<P: Params>(
+        &mut self,
+        query: &str,
+        params: P,
+    ) -> Result<(ResultSetMetaData, Vec<Row>), Error> {
+        let mut stmt = self.prepare(query)?;
+        let rows = stmt.query(params)?;
+        Ok((stmt.metadata(), rows.collect()))
+    }
+
+    // limit rows after offset of a select and the count of all its rows, e.g. for
+    // a data grid. The count is a SELECT COUNT(*) FROM (query), the server runs
+    // the whole query for it, so it costs as much as fetching every row but
+    // their transfer. Without a transaction() the two queries may see different
+    // data when ExecutionMode::AutoCommit commits in between.
+    // A query with its own ROWS/OFFSET/FETCH, FOR UPDATE or WITH LOCK is rejected.
+    pub fn query_count_and_page<P: Params + Clone>(
+        &mut self,
+        query: &str,
+        params: P,
+        offset: u64,
+        limit: u64,
+    ) -> Result<(u64, Vec<Row>), Error> {
+        let (count_query, page_query) = utils::count_and_page_queries(query, offset, limit)?;
+        let total: i64 = {
+            let mut stmt = self.prepare(&count_query)?;
+            let row = stmt.query(params.clone())?.next();
+            match row {
+                Some(row) => row.get(0)?,
+                None => 0,
+            }
+        };
+        if limit == 0 {
+            return Ok((total as u64, Vec::new()));
+        }
+        let (_, rows) = self.query_all(&page_query, params)?;
+        Ok((total as u64, rows))
+    }
+
+    // RDB$SET_CONTEXT, None clears the variable. USER_SESSION variables last
+    // until the connection is closed or reset_session(), USER_TRANSACTION ones until the
+    // end of the transaction
+    pub fn set_context(
+        &mut self,
+        namespace: &str,
+        key: &str,
+        value: Option<&str>,
+    ) -> Result<(), Error> {
+        let value = value.map_or(Param::Null, Param::from);
+        let mut stmt = self.prepare("SELECT RDB$SET_CONTEXT(?, ?, ?) FROM RDB$DATABASE")?;
+        stmt.execute((namespace, key, value))?;
+        Ok(())
+    }
+
+    // RDB$GET_CONTEXT, None for a variable which isn't set
+    pub fn get_context(&mut self, namespace: &str, key: &str) -> Result<Option<String>, Error> {
+        let mut stmt = self.prepare("SELECT RDB$GET_CONTEXT(?, ?) FROM RDB$DATABASE")?;
+        let row = stmt.query((namespace, key))?.next();
+        match row {
+            Some(row) => row.get(0),
+            None => Ok(None),
+        }
+    }
+
+    // e.g. object_exists(ObjectKind::Table, "foo") for migrations, the name
+    // is matched as written in sql (case-insensitively unless "quoted")
+    pub fn object_exists(&mut self, kind: ObjectKind, name: &str) -> Result<bool, Error> {
+        let (table, column) = kind.catalog();
+        let (query, name) = utils::object_exists_query(table, column, name);
+        let mut stmt = self.prepare(&query)?;
+        let exists = stmt.query((name.as_str(),))?.next().is_some();
+        Ok(exists)
+    }
+
+    // ids of the transactions left in limbo by an interrupted two-phase commit,
+    // as recorded in RDB$TRANSACTIONS
+    pub fn recover_limbo_transactions(&mut self) -> Result<Vec<u64>, Error> {
+        let mut stmt = self.prepare(
+            "SELECT RDB$TRANSACTION_ID FROM RDB$TRANSACTIONS
+                WHERE RDB$TRANSACTION_STATE = 1 ORDER BY RDB$TRANSACTION_ID",
+        )?;
+        let mut ids = Vec::new();
+        for row in stmt.query(())? {
+            let id: i64 = row.get(0)?;
+            ids.push(id as u64);
+        }
+        Ok(ids)
+    }
+
+    pub fn commit_limbo(&mut self, transaction_id: u64) -> Result<(), Error> {
+        let mut wp = self.wp.borrow_mut();
+        wp.op_reconnect(transaction_id)?;
+        let (trans_handle, _, _) = wp.op_response()?;
+        wp.op_commit(trans_handle)?;
+        wp.op_response()?;
+        Ok(())
+    }
+
+    pub fn rollback_limbo(&mut self, transaction_id: u64) -> Result<(), Error> {
+        let mut wp = self.wp.borrow_mut();
+        wp.op_reconnect(transaction_id)?;
+        let (trans_handle, _, _) = wp.op_response()?;
+        wp.op_rollback(trans_handle)?;
+        wp.op_response()?;
+        Ok(())
+    }
+
+    // subscribe to events posted by POST_EVENT, the connection is
+    // occupied by the conduit until it is dropped
+    pub fn event_conduit(&mut self, names: &[&str]) -> Result<EventConduit<'_>, Error> {
+        EventConduit::new(self, names)
     }
 
     pub fn transaction(&mut self) -> Result<Transaction, Error> {
         Transaction::new(self)
     }
 
+    pub fn ping(&self) -> Result<(), Error> {
+        let mut wp = self.wp.borrow_mut();
+        wp.op_ping()?;
+        wp.op_response()?;
+        Ok(())
+    }
+
+    // ping and a trivial query, errors are reported as false
+    pub fn is_valid(&mut self) -> bool {
+        if self.ping().is_err() {
+            return false;
+        }
+        match self.prepare("SELECT 1 FROM RDB$DATABASE") {
+            Ok(mut stmt) => stmt.query(()).is_ok(),
+            Err(_) => false,
+        }
+    }
+
+    // the server defers some responses (Firebird 3 or later), statement handles
+    // are then known only after the following request
+    pub fn uses_lazy_send(&self) -> bool {
+        self.wp.borrow().accept_type == PTYPE_LAZY_SEND
+    }
+
+    // reply buffer length of info requests and prepare (1024 by default),
+    // larger for long plans or many attachments, up to 1MB
+    pub fn set_info_buffer_len(&mut self, len: u32) -> Result<(), Error> {
+        if !conn_params::is_valid_info_buffer_len(len) {
+            return Err(Error::ValueError(ValueError::new(&format!(
+                "info buffer length {} is out of 1 to {}",
+                len, MAX_INFO_BUFFER_LEN
+            ))));
+        }
+        self.wp.borrow_mut().info_buffer_len = len;
+        Ok(())
+    }
+
+    // warnings reported by the server since the last call
+    pub fn take_warnings(&self) -> Vec<String> {
+        std::mem::take(&mut self.wp.borrow_mut().warnings)
+    }
+
+    // Direct access to the wire protocol for op sequences not modeled by this API.
+    // Every op_* request must be followed by reading its response, anything
+    // else desyncs the protocol and the connection becomes unusable.
+    #[cfg(feature = "unstable")]
+    pub fn with_wire_protocol<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut WireProtocol) -> R,
+    {
+        f(&mut self.wp.borrow_mut())
+    }
+
+    pub fn database_info(&self) -> Result<DatabaseInfo, Error> {
+        let mut wp = self.wp.borrow_mut();
+        let db_handle = wp.db_handle;
+        let buf = wp.info_request(OP_INFO_DATABASE, db_handle, &DatabaseInfo::items())?;
+        DatabaseInfo::parse(&buf)
+    }
+
     // methods for Statement
 
     pub(crate) fn _execute_statement(
@@ -262,26 +659,95 @@ impl Connection {
         Ok(wp.rowcount(stmt_handle, stmt_type)?)
     }
 
+    // EXECUTE PROCEDURE, the output parameters are returned in op_sql_response
+    pub(crate) fn _execute_procedure(
+        &self,
+        trans_handle: i32,
+        stmt_handle: i32,
+        params: &[(Vec<u8>, Vec<u8>, bool)],
+        xsqlda: &[XSQLVar],
+    ) -> Result<Vec<CellValue>, Error> {
+        let blr = calc_blr(xsqlda)?;
+        let mut wp = self.wp.borrow_mut();
+        wp.op_execute2(stmt_handle, trans_handle, params, &blr)?;
+        let row = wp.op_sql_response(xsqlda)?;
+        wp.op_response()?;
+        Ok(row)
+    }
+
     pub(crate) fn _fetch(
         &self,
         stmt_handle: i32,
         blr: &Vec<u8>,
         xsqlda: &[XSQLVar],
-    ) -> Result<(Vec<Vec<CellValue>>, bool), Error> {
+    ) -> Result<FetchResponse, Error> {
         let mut wp = self.wp.borrow_mut();
         wp.op_fetch(stmt_handle, &blr)?;
         wp.op_fetch_response(xsqlda)
     }
 
+    pub(crate) fn _create_blob<R: std::io::Read>(
+        &self,
+        reader: &mut R,
+        trans_handle: i32,
+    ) -> Result<BlobId, Error> {
+        let mut wp = self.wp.borrow_mut();
+        wp.create_blob(reader, trans_handle)
+    }
+
     pub(crate) fn _get_blob_segments(
         &self,
-        blob_id: &Vec<u8>,
+        blob_id: &BlobId,
         trans_handle: i32,
     ) -> Result<Vec<u8>, Error> {
         let mut wp = self.wp.borrow_mut();
         wp.get_blob_segments(blob_id, trans_handle)
     }
 
+    pub(crate) fn _get_blob_segments_limited(
+        &self,
+        blob_id: &BlobId,
+        trans_handle: i32,
+        max_length: usize,
+    ) -> Result<Option<Vec<u8>>, Error> {
+        let mut wp = self.wp.borrow_mut();
+        wp.get_blob_segments_limited(blob_id, trans_handle, max_length)
+    }
+
+    pub(crate) fn _open_blob(&self, blob_id: &BlobId, trans_handle: i32) -> Result<i32, Error> {
+        let mut wp = self.wp.borrow_mut();
+        wp.open_blob(blob_id, trans_handle)
+    }
+
+    pub(crate) fn _read_blob_segment(&self, blob_handle: i32) -> Result<(Vec<u8>, bool), Error> {
+        let mut wp = self.wp.borrow_mut();
+        wp.read_blob_segment(blob_handle)
+    }
+
+    pub(crate) fn _close_blob(&self, blob_handle: i32) -> Result<(), Error> {
+        let mut wp = self.wp.borrow_mut();
+        wp.close_blob(blob_handle)
+    }
+
+    pub(crate) fn _event_channel(&self) -> Result<WireChannel, Error> {
+        let mut wp = self.wp.borrow_mut();
+        wp.event_channel()
+    }
+
+    pub(crate) fn _que_events(&self, event_block: &[u8], event_id: i32) -> Result<i32, Error> {
+        let mut wp = self.wp.borrow_mut();
+        wp.op_que_events(event_block, event_id)?;
+        let (event_id, _, _) = wp.op_response()?;
+        Ok(event_id)
+    }
+
+    pub(crate) fn _cancel_events(&self, event_id: i32) -> Result<(), Error> {
+        let mut wp = self.wp.borrow_mut();
+        wp.op_cancel_events(event_id)?;
+        wp.op_response()?;
+        Ok(())
+    }
+
     pub(crate) fn _free_statement(&self, stmt_handle: i32, drop_type: i32) -> () {
         let mut wp = self.wp.borrow_mut();
         wp.op_free_statement(stmt_handle, drop_type).unwrap();
@@ -0,0 +1,158 @@
+// MIT License
+//
+// Copyright (c) 2021 Hajime Nakagami<nakagami@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use super::error::{Error, ValueError};
+use super::*;
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct DatabaseInfo {
+    pub page_size: i64,
+    pub sql_dialect: i64,
+    pub read_only: bool,
+    pub oldest_transaction: i64, // oldest interesting transaction
+    pub oldest_active: i64,
+    pub oldest_snapshot: i64,
+    pub next_transaction: i64,
+    pub active_tran_count: i64,
+    pub ods_version: i64,
+    pub ods_minor_version: i64,
+    pub att_charset: i64, // character set id of the attachment, RDB$CHARACTER_SET_ID
+}
+
+impl DatabaseInfo {
+    pub(crate) fn items() -> Vec<u8> {
+        vec![
+            ISC_INFO_PAGE_SIZE,
+            ISC_INFO_DB_SQL_DIALECT,
+            ISC_INFO_DB_READ_ONLY,
+            ISC_INFO_OLDEST_TRANSACTION,
+            ISC_INFO_OLDEST_ACTIVE,
+            ISC_INFO_OLDEST_SNAPSHOT,
+            ISC_INFO_NEXT_TRANSACTION,
+            ISC_INFO_ACTIVE_TRAN_COUNT,
+            ISC_INFO_ODS_VERSION,
+            ISC_INFO_ODS_MINOR_VERSION,
+            ISC_INFO_ATT_CHARSET,
+        ]
+    }
+
+    pub(crate) fn parse(buf: &[u8]) -> Result<DatabaseInfo, Error> {
+        let mut info = DatabaseInfo {
+            page_size: 0,
+            sql_dialect: 0,
+            read_only: false,
+            oldest_transaction: 0,
+            oldest_active: 0,
+            oldest_snapshot: 0,
+            next_transaction: 0,
+            active_tran_count: 0,
+            ods_version: 0,
+            ods_minor_version: 0,
+            att_charset: 0,
+        };
+
+        let mut i: usize = 0;
+        while i < buf.len() && buf[i] != ISC_INFO_END {
+            let item = buf[i];
+            if item == ISC_INFO_TRUNCATED {
+                return Err(Error::ValueError(ValueError::new(
+                    "database info truncated",
+                )));
+            }
+            if i + 3 > buf.len() {
+                break;
+            }
+            let ln = utils::bytes_to_uint16(&buf[i + 1..i + 3]) as usize;
+            i += 3;
+            if i + ln > buf.len() {
+                return Err(Error::ValueError(ValueError::new(
+                    "invalid database info buffer",
+                )));
+            }
+            let v = &buf[i..i + ln];
+            match item {
+                ISC_INFO_PAGE_SIZE => info.page_size = utils::bytes_to_int(v),
+                ISC_INFO_DB_SQL_DIALECT => info.sql_dialect = utils::bytes_to_int(v),
+                ISC_INFO_DB_READ_ONLY => info.read_only = utils::bytes_to_int(v) != 0,
+                ISC_INFO_OLDEST_TRANSACTION => info.oldest_transaction = utils::bytes_to_int(v),
+                ISC_INFO_OLDEST_ACTIVE => info.oldest_active = utils::bytes_to_int(v),
+                ISC_INFO_OLDEST_SNAPSHOT => info.oldest_snapshot = utils::bytes_to_int(v),
+                ISC_INFO_NEXT_TRANSACTION => info.next_transaction = utils::bytes_to_int(v),
+                ISC_INFO_ACTIVE_TRAN_COUNT => info.active_tran_count = utils::bytes_to_int(v),
+                ISC_INFO_ODS_VERSION => info.ods_version = utils::bytes_to_int(v),
+                ISC_INFO_ODS_MINOR_VERSION => info.ods_minor_version = utils::bytes_to_int(v),
+                ISC_INFO_ATT_CHARSET => info.att_charset = utils::bytes_to_int(v),
+                _ => {}
+            }
+            i += ln;
+        }
+
+        Ok(info)
+    }
+
+    // ODS 13 (Firebird 4) or later allows identifiers up to 63 characters
+    pub fn max_identifier_length(&self) -> usize {
+        if self.ods_version >= 13 {
+            63
+        } else {
+            31
+        }
+    }
+}
+
+#[test]
+fn test_database_info() {
+    let buf = [
+        &[ISC_INFO_PAGE_SIZE, 4, 0, 0, 0x20, 0, 0][..],
+        &[ISC_INFO_DB_SQL_DIALECT, 1, 0, 3],
+        &[ISC_INFO_DB_READ_ONLY, 1, 0, 1],
+        &[ISC_INFO_OLDEST_TRANSACTION, 4, 0, 0x10, 0, 0, 0],
+        &[ISC_INFO_OLDEST_ACTIVE, 4, 0, 0x11, 0, 0, 0],
+        &[ISC_INFO_OLDEST_SNAPSHOT, 4, 0, 0x11, 0, 0, 0],
+        &[ISC_INFO_NEXT_TRANSACTION, 4, 0, 0x00, 0x01, 0, 0],
+        &[ISC_INFO_ACTIVE_TRAN_COUNT, 4, 0, 2, 0, 0, 0],
+        &[ISC_INFO_ODS_VERSION, 4, 0, 13, 0, 0, 0],
+        &[ISC_INFO_ODS_MINOR_VERSION, 4, 0, 1, 0, 0, 0],
+        &[ISC_INFO_ATT_CHARSET, 2, 0, 4, 0],
+        &[ISC_INFO_END],
+    ]
+    .concat();
+    let info = DatabaseInfo::parse(&buf).unwrap();
+    assert_eq!(info.page_size, 8192);
+    assert_eq!(info.sql_dialect, 3);
+    assert!(info.read_only);
+    assert_eq!(info.oldest_transaction, 16);
+    assert_eq!(info.oldest_active, 17);
+    assert_eq!(info.oldest_snapshot, 17);
+    assert_eq!(info.next_transaction, 256);
+    assert_eq!(info.active_tran_count, 2);
+    assert_eq!(info.ods_version, 13);
+    assert_eq!(info.ods_minor_version, 1);
+    assert_eq!(info.att_charset, 4);
+    assert_eq!(info.max_identifier_length(), 63);
+
+    let info =
+        DatabaseInfo::parse(&[ISC_INFO_ODS_VERSION, 4, 0, 12, 0, 0, 0, ISC_INFO_END]).unwrap();
+    assert_eq!(info.max_identifier_length(), 31);
+
+    assert!(DatabaseInfo::parse(&[ISC_INFO_TRUNCATED, ISC_INFO_END]).is_err());
+}
@@ -245,3 +245,141 @@ pub fn decimal128_to_decimal(b: &[u8]) -> Result<rust_decimal::Decimal, ValueErr
     }
     Ok(rust_decimal::Decimal::new(num, exponent as u32))
 }
+
+fn int_to_dpd(n: u16) -> u16 {
+    // Convert int (0-999) to DPD encoded value, the inverse of dpd_to_int()
+    let (a, b, c) = (n / 100, n / 10 % 10, n % 10);
+    match (a > 7, b > 7, c > 7) {
+        (false, false, false) => (a << 7) | (b << 4) | c,
+        (false, false, true) => (a << 7) | (b << 4) | 0b1000 | (c & 1),
+        (false, true, false) => (a << 7) | ((c >> 1) << 5) | ((b & 1) << 4) | 0b1010 | (c & 1),
+        (true, false, false) => ((c >> 1) << 8) | ((a & 1) << 7) | (b << 4) | 0b1100 | (c & 1),
+        (true, true, false) => ((c >> 1) << 8) | ((a & 1) << 7) | ((b & 1) << 4) | 0b1110 | (c & 1),
+        (true, false, true) => {
+            ((b >> 1) << 8) | ((a & 1) << 7) | (0b01 << 5) | ((b & 1) << 4) | 0b1110 | (c & 1)
+        }
+        (false, true, true) => (a << 7) | (0b10 << 5) | ((b & 1) << 4) | 0b1110 | (c & 1),
+        (true, true, true) => ((a & 1) << 7) | (0b11 << 5) | ((b & 1) << 4) | 0b1110 | (c & 1),
+    }
+}
+
+fn decimal_to_sign_digits_exponent(
+    d: &rust_decimal::Decimal,
+    max_digits: u32,
+) -> Result<(u128, u128, i32), ValueError> {
+    let sign = if d.is_sign_negative() { 1 } else { 0 };
+    let mut digits = d.mantissa().unsigned_abs();
+    let mut exponent = -(d.scale() as i32);
+    while digits >= 10u128.pow(max_digits) {
+        if !digits.is_multiple_of(10) {
+            return Err(ValueError::new(&format!(
+                "{} has more than {} digits",
+                d, max_digits
+            )));
+        }
+        digits /= 10;
+        exponent += 1;
+    }
+    Ok((sign, digits, exponent))
+}
+
+fn encode_decimal(
+    sign: u128,
+    digits: u128,
+    biased_exponent: u128,
+    num_bits: u32,
+    exponent_bits: u32,
+) -> u128 {
+    // num_bits: bit length of the whole value
+    // exponent_bits: bit length of the exponent continuation field
+    // https://en.wikipedia.org/wiki/Decimal128_floating-point_format
+    let num_segments = (num_bits - 6 - exponent_bits) / 10;
+    let mut v = digits;
+    let mut dpd_bits: u128 = 0;
+    for i in 0..num_segments {
+        dpd_bits |= (int_to_dpd((v % 1000) as u16) as u128) << (i * 10);
+        v /= 1000;
+    }
+    let msd = v; // most significant digit
+    let exponent_high = biased_exponent >> exponent_bits;
+    let exponent_low = biased_exponent & ((1 << exponent_bits) - 1);
+    let cf = if msd < 8 {
+        (exponent_high << 3) | msd
+    } else {
+        0b11000 | (exponent_high << 1) | (msd & 1)
+    };
+
+    (sign << (num_bits - 1))
+        | (cf << (num_bits - 6))
+        | (exponent_low << (num_bits - 6 - exponent_bits))
+        | dpd_bits
+}
+
+pub fn decimal_to_decimal64(d: &rust_decimal::Decimal) -> Result<[u8; 8], ValueError> {
+    // https://en.wikipedia.org/wiki/Decimal64_floating-point_format
+    let (sign, digits, exponent) = decimal_to_sign_digits_exponent(d, 16)?;
+    let v = encode_decimal(sign, digits, (exponent + 398) as u128, 64, 8);
+    Ok((v as u64).to_be_bytes())
+}
+
+pub fn decimal_to_decimal128(d: &rust_decimal::Decimal) -> Result<[u8; 16], ValueError> {
+    // https://en.wikipedia.org/wiki/Decimal128_floating-point_format
+    let (sign, digits, exponent) = decimal_to_sign_digits_exponent(d, 34)?;
+    let v = encode_decimal(sign, digits, (exponent + 6176) as u128, 128, 12);
+    Ok(v.to_be_bytes())
+}
+
+#[test]
+fn test_int_to_dpd() {
+    for n in 0..1000 {
+        assert_eq!(dpd_to_int(int_to_dpd(n)).unwrap(), n);
+    }
+    assert_eq!(int_to_dpd(999), 0x0ff);
+}
+
+#[test]
+fn test_decimal_to_decimal64() {
+    use rust_decimal::Decimal;
+    use std::str::FromStr;
+    assert_eq!(
+        decimal_to_decimal64(&Decimal::from(1)).unwrap(),
+        [0x22, 0x38, 0, 0, 0, 0, 0, 0x01]
+    );
+    assert_eq!(
+        decimal_to_decimal64(&Decimal::from(-1)).unwrap(),
+        [0xa2, 0x38, 0, 0, 0, 0, 0, 0x01]
+    );
+    // 12345E-2
+    assert_eq!(
+        decimal_to_decimal64(&Decimal::from_str("123.45").unwrap()).unwrap(),
+        [0x22, 0x30, 0, 0, 0, 0, 0x49, 0xc5]
+    );
+    // 9999999999999999, the largest coefficient
+    assert_eq!(
+        decimal_to_decimal64(&Decimal::from(9999999999999999i64)).unwrap(),
+        [0x6e, 0x38, 0xff, 0x3f, 0xcf, 0xf3, 0xfc, 0xff]
+    );
+    // trailing zeros go to the exponent
+    // 1000000000000000E2
+    assert_eq!(
+        decimal_to_decimal64(&Decimal::from(100000000000000000i64)).unwrap(),
+        [0x26, 0x40, 0, 0, 0, 0, 0, 0]
+    );
+    assert!(decimal_to_decimal64(&Decimal::from_str("12345678901234567").unwrap()).is_err());
+}
+
+#[test]
+fn test_decimal_to_decimal128() {
+    use rust_decimal::Decimal;
+    use std::str::FromStr;
+    assert_eq!(
+        decimal_to_decimal128(&Decimal::from(1)).unwrap(),
+        [0x22, 0x08, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x01]
+    );
+    assert_eq!(
+        decimal_to_decimal128(&Decimal::from_str("-0.5").unwrap()).unwrap(),
+        [0xa2, 0x07, 0xc0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x05]
+    );
+    // 29 digits fit in DECFLOAT(34)
+    assert!(decimal_to_decimal128(&Decimal::MAX).is_ok());
+}
@@ -13,7 +13,7 @@
 
 use maplit::hashmap;
 
-pub fn error_message_by_id(id: u32) -> &'static str {
+pub fn error_message_by_id(id: u32) -> String {
     let map = hashmap! {
          335544321=> r#"arithmetic exception, numeric overflow, or string truncation\n"#,
          335544322=> r#"invalid database key\n"#,
@@ -1494,5 +1494,21 @@ pub fn error_message_by_id(id: u32) -> &'static str {
          337182759=> r#"parameter "@1" is incompatible with action "@2"\n"#,
          337182760=> r#"mandatory switch "@1" is missing\n"#,
     };
-    map[&id]
+    match map.get(&id) {
+        Some(message) => message.to_string(),
+        // a code newer than this table, keep it in the message
+        None => format!(r#"Firebird error {}\n"#, id),
+    }
+}
+
+#[test]
+fn test_error_message_by_id() {
+    assert_eq!(
+        error_message_by_id(335544324),
+        r#"invalid database handle (no active connection)\n"#
+    );
+    assert_eq!(
+        error_message_by_id(335599999),
+        r#"Firebird error 335599999\n"#
+    );
 }
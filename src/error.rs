@@ -30,6 +30,29 @@ pub enum Error {
     ValueError(ValueError),
     ParamError(ParamError),
     UrlError(UrlError),
+    /// the connection transaction has ended, see Connection::reset_transaction()
+    NoTransaction,
+}
+
+// transient conflicts, the transaction can be retried
+const RETRIABLE_GDS_CODES: [u32; 5] = [
+    335544336, // deadlock
+    335544345, // lock conflict on no wait transaction
+    335544451, // update conflicts with concurrent update
+    335544510, // lock time-out on wait transaction
+    335544878, // concurrent transaction number is @1
+];
+
+impl Error {
+    pub fn is_retriable(&self) -> bool {
+        match self {
+            Error::FirebirdError(e) => e
+                .gds_codes
+                .iter()
+                .any(|code| RETRIABLE_GDS_CODES.contains(code)),
+            _ => false,
+        }
+    }
 }
 
 impl From<UrlError> for Error {
@@ -54,12 +77,23 @@ impl From<FirebirdError> for Error {
 pub struct FirebirdError {
     pub message: String,
     pub sql_code: i32,
+    pub gds_codes: Vec<u32>,
 }
 
 impl FirebirdError {
     pub fn new(message: &str, sql_code: i32) -> FirebirdError {
         let message = message.to_string();
-        FirebirdError { message, sql_code }
+        FirebirdError {
+            message,
+            sql_code,
+            gds_codes: Vec::new(),
+        }
+    }
+
+    pub fn with_gds_codes(message: &str, sql_code: i32, gds_codes: Vec<u32>) -> FirebirdError {
+        let mut err = FirebirdError::new(message, sql_code);
+        err.gds_codes = gds_codes;
+        err
     }
 }
 
@@ -82,6 +116,12 @@ impl ValueError {
     }
 }
 
+impl From<ParamError> for Error {
+    fn from(x: ParamError) -> Error {
+        Error::ParamError(x)
+    }
+}
+
 #[derive(Eq, PartialEq, Clone, Debug)]
 pub struct ParamError {
     pub message: String,
@@ -104,6 +144,8 @@ pub enum UrlError {
     /// (feature_name, value)
     InvalidValue(String, String),
     UnknownParameter(String),
+    /// connections this wire protocol client can't make
+    Unsupported(String),
     BadUrl,
 }
 
@@ -131,6 +173,7 @@ impl fmt::Display for UrlError {
             UrlError::UnknownParameter(ref parameter) => {
                 write!(f, "Unknown URL parameter `{}'", parameter)
             }
+            UrlError::Unsupported(ref s) => write!(f, "{}", s),
             UrlError::BadUrl => write!(f, "Invalid or incomplete connection URL"),
         }
     }
@@ -147,3 +190,14 @@ impl From<ParseError> for UrlError {
         UrlError::ParseError(x)
     }
 }
+
+#[test]
+fn test_is_retriable() {
+    let deadlock = FirebirdError::with_gds_codes("deadlock", -913, vec![335544336, 335544878]);
+    assert!(Error::FirebirdError(deadlock).is_retriable());
+
+    let unique = FirebirdError::with_gds_codes("violation", -803, vec![335544665]);
+    assert!(!Error::FirebirdError(unique).is_retriable());
+
+    assert!(!Error::ValueError(ValueError::new("value")).is_retriable());
+}
@@ -0,0 +1,180 @@
+// MIT License
+//
+// Copyright (c) 2021 Hajime Nakagami<nakagami@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use super::connection::Connection;
+use super::error::{Error, ValueError};
+use super::utils;
+use super::wirechannel::WireChannel;
+use super::wireprotocol::recv_event;
+
+// op_que_events id of the request, a conduit has its own auxiliary connection
+const LOCAL_EVENT_ID: i32 = 1;
+
+// counts of the events posted by POST_EVENT, delivered on the auxiliary connection
+pub struct EventConduit<'conn> {
+    conn: &'conn Connection,
+    channel: WireChannel,
+    names: Vec<String>,
+    counts: HashMap<String, u32>, // as of the last op_event
+    event_id: i32,                // returned by op_que_events, for op_cancel_events
+    queued: bool,                 // op_que_events sent, its op_event not received yet
+    cancelled: bool,
+}
+
+pub(crate) fn check_event_names(names: &[&str]) -> Result<Vec<String>, Error> {
+    if names.is_empty() {
+        return Err(Error::ValueError(ValueError::new("no event names")));
+    }
+    for name in names {
+        if name.is_empty() || name.len() > 255 {
+            return Err(Error::ValueError(ValueError::new(&format!(
+                "invalid event name: {:?}",
+                name
+            ))));
+        }
+    }
+    Ok(names.iter().map(|name| name.to_string()).collect())
+}
+
+// counts since the previous op_event by name, and take the new counts
+pub(crate) fn posted_counts(
+    names: &[String],
+    counts: &mut HashMap<String, u32>,
+    new_counts: &HashMap<String, u32>,
+) -> HashMap<String, u32> {
+    let mut posted = HashMap::new();
+    for name in names {
+        let count = new_counts.get(name).copied().unwrap_or(0);
+        let previous = counts.insert(name.to_string(), count).unwrap_or(0);
+        posted.insert(name.to_string(), count.saturating_sub(previous));
+    }
+    posted
+}
+
+impl EventConduit<'_> {
+    pub(crate) fn new<'conn>(
+        conn: &'conn Connection,
+        names: &[&str],
+    ) -> Result<EventConduit<'conn>, Error> {
+        let names = check_event_names(names)?;
+        let channel = conn._event_channel()?;
+        let mut conduit = EventConduit {
+            conn,
+            channel,
+            names,
+            counts: HashMap::new(),
+            event_id: 0,
+            queued: false,
+            cancelled: false,
+        };
+        // the first op_event reports the counts so far
+        conduit.queue()?;
+        let (_, counts) = recv_event(&mut conduit.channel)?;
+        conduit.counts = counts;
+        conduit.queued = false;
+        Ok(conduit)
+    }
+
+    fn queue(&mut self) -> Result<(), Error> {
+        let block = utils::event_block(&self.names, &self.counts);
+        self.event_id = self.conn._que_events(&block, LOCAL_EVENT_ID)?;
+        self.queued = true;
+        Ok(())
+    }
+
+    fn receive(&mut self) -> Result<HashMap<String, u32>, Error> {
+        let (_, counts) = recv_event(&mut self.channel)?;
+        self.queued = false;
+        Ok(posted_counts(&self.names, &mut self.counts, &counts))
+    }
+
+    // block until any of the events is posted (and committed),
+    // the number of times each was posted since the previous wait
+    pub fn wait(&mut self) -> Result<HashMap<String, u32>, Error> {
+        if !self.queued {
+            self.queue()?;
+        }
+        self.receive()
+    }
+
+    // like wait(), None when none of the events is posted within timeout.
+    // The events stay queued for the next wait
+    pub fn wait_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<Option<HashMap<String, u32>>, Error> {
+        if !self.queued {
+            self.queue()?;
+        }
+        if !self.channel.wait_readable(timeout)? {
+            return Ok(None);
+        }
+        self.receive().map(Some)
+    }
+
+    // stop listening, queued events are cancelled on the server
+    pub fn cancel(mut self) -> Result<(), Error> {
+        self.cancelled = true;
+        if !self.queued {
+            return Ok(());
+        }
+        self.conn._cancel_events(self.event_id)
+    }
+}
+
+impl Drop for EventConduit<'_> {
+    fn drop(&mut self) {
+        if !self.cancelled && self.queued {
+            let _ = self.conn._cancel_events(self.event_id);
+        }
+    }
+}
+
+#[test]
+fn test_check_event_names() {
+    assert_eq!(
+        check_event_names(&["a", "b"]).unwrap(),
+        vec!["a".to_string(), "b".to_string()]
+    );
+    assert!(check_event_names(&[]).is_err());
+    assert!(check_event_names(&[""]).is_err());
+    assert!(check_event_names(&[&"x".repeat(256)]).is_err());
+}
+
+#[test]
+fn test_posted_counts() {
+    let names = vec!["a".to_string(), "b".to_string()];
+    let mut counts = HashMap::new();
+    counts.insert("a".to_string(), 3);
+    counts.insert("b".to_string(), 1);
+    let mut new_counts = HashMap::new();
+    new_counts.insert("a".to_string(), 5);
+    new_counts.insert("b".to_string(), 1);
+
+    let posted = posted_counts(&names, &mut counts, &new_counts);
+    assert_eq!(posted["a"], 2);
+    assert_eq!(posted["b"], 0);
+    assert_eq!(counts["a"], 5);
+}
@@ -0,0 +1,129 @@
+// MIT License
+//
+// Copyright (c) 2021-2024 Hajime Nakagami<nakagami@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use super::connection_async::ConnectionAsync;
+use super::error::Error;
+use super::event_conduit::{check_event_names, posted_counts};
+use super::utils;
+use super::wirechannel_async::WireChannelAsync;
+use super::wireprotocol_async::recv_event;
+
+const LOCAL_EVENT_ID: i32 = 1;
+
+macro_rules! debug_print {
+    //    ($( $args:expr ),*) => { println!( $( $args ),* ); }
+    ($( $args:expr ),*) => {};
+}
+
+// async counterpart of EventConduit
+pub struct EventConduitAsync<'conn> {
+    conn: &'conn ConnectionAsync,
+    channel: WireChannelAsync,
+    names: Vec<String>,
+    counts: HashMap<String, u32>, // as of the last op_event
+    event_id: i32,                // returned by op_que_events, for op_cancel_events
+    queued: bool,                 // op_que_events sent, its op_event not received yet
+    cancelled: bool,
+}
+
+impl EventConduitAsync<'_> {
+    pub(crate) async fn new<'conn>(
+        conn: &'conn ConnectionAsync,
+        names: &[&str],
+    ) -> Result<EventConduitAsync<'conn>, Error> {
+        let names = check_event_names(names)?;
+        let channel = conn._event_channel().await?;
+        let mut conduit = EventConduitAsync {
+            conn,
+            channel,
+            names,
+            counts: HashMap::new(),
+            event_id: 0,
+            queued: false,
+            cancelled: false,
+        };
+        // the first op_event reports the counts so far
+        conduit.queue().await?;
+        let (_, counts) = recv_event(&mut conduit.channel).await?;
+        conduit.counts = counts;
+        conduit.queued = false;
+        Ok(conduit)
+    }
+
+    async fn queue(&mut self) -> Result<(), Error> {
+        let block = utils::event_block(&self.names, &self.counts);
+        self.event_id = self.conn._que_events(&block, LOCAL_EVENT_ID).await?;
+        self.queued = true;
+        Ok(())
+    }
+
+    async fn receive(&mut self) -> Result<HashMap<String, u32>, Error> {
+        let (_, counts) = recv_event(&mut self.channel).await?;
+        self.queued = false;
+        Ok(posted_counts(&self.names, &mut self.counts, &counts))
+    }
+
+    // until any of the events is posted (and committed),
+    // the number of times each was posted since the previous wait
+    pub async fn wait(&mut self) -> Result<HashMap<String, u32>, Error> {
+        if !self.queued {
+            self.queue().await?;
+        }
+        self.receive().await
+    }
+
+    // like wait(), None when none of the events is posted within timeout.
+    // The events stay queued for the next wait
+    pub async fn wait_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<Option<HashMap<String, u32>>, Error> {
+        if !self.queued {
+            self.queue().await?;
+        }
+        if !self.channel.wait_readable(timeout).await? {
+            return Ok(None);
+        }
+        self.receive().await.map(Some)
+    }
+
+    // stop listening, queued events are cancelled on the server
+    pub async fn cancel(mut self) -> Result<(), Error> {
+        self.cancelled = true;
+        if !self.queued {
+            return Ok(());
+        }
+        self.conn._cancel_events(self.event_id).await
+    }
+}
+
+impl Drop for EventConduitAsync<'_> {
+    fn drop(&mut self) {
+        // see Drop for WireProtocolAsync, the events end with the auxiliary connection
+        if !self.cancelled {
+            debug_print!("EventConduitAsync dropped without cancel()");
+        }
+    }
+}
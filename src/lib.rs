@@ -22,13 +22,17 @@
 #![feature(async_drop)]
 #![allow(dead_code)]
 
+mod blob_reader;
 mod cellvalue;
+mod charset;
 mod conn_params;
 mod connection;
 mod crypt_translater;
+mod database_info;
 mod decfloat;
 mod errmsgs;
 mod error;
+mod event_conduit;
 mod param;
 mod params;
 mod row;
@@ -41,17 +45,50 @@ mod wirechannel;
 mod wireprotocol;
 mod xsqlvar;
 
+mod blob_reader_async;
 mod connection_async;
+mod event_conduit_async;
+mod pool_async;
 mod statement_async;
 mod transaction_async;
 mod wirechannel_async;
 mod wireprotocol_async;
 
+pub use crate::blob_reader::BlobReader;
+pub use crate::blob_reader_async::BlobReaderAsync;
+pub use crate::cellvalue::BlobId;
+pub use crate::cellvalue::CellValue;
+pub use crate::charset::Charset;
 pub use crate::connection::Connection;
+pub use crate::connection::ExecutionMode;
+pub use crate::connection::ObjectKind;
 pub use crate::connection_async::ConnectionAsync;
+pub use crate::database_info::DatabaseInfo;
 pub use crate::error::Error;
+pub use crate::event_conduit::EventConduit;
+pub use crate::event_conduit_async::EventConduitAsync;
+pub use crate::param::blob_file;
 pub use crate::param::Param;
 pub use crate::param::ToSqlParam;
+pub use crate::params::{sql_in, sql_rows, ToParams};
+pub use crate::pool_async::{PoolAsync, PooledConnectionAsync};
+pub use crate::row::ResultSetMetaData;
+#[cfg(feature = "unstable")]
+pub use crate::wireprotocol::WireProtocol;
+pub use crate::xsqlvar::SqlType;
+
+// op_fetch_response status: more rows can be fetched, or the end of the cursor
+const FETCH_OK: u32 = 0;
+const FETCH_NO_MORE_ROWS: u32 = 100;
+
+// info replies are requested again with a doubled buffer up to this length
+const MAX_INFO_BUFFER_LEN: u32 = 1024 * 1024;
+
+// Architecture type (accept_architecture), the only one offered by op_connect.
+// Generic is XDR, the protocol itself is big endian whatever the server platform,
+// the info buffers and the BLR and message data in them are little endian
+// (the bytes_to_* helpers without b).
+const ARCH_GENERIC: i32 = 1;
 
 // Protocol Types (accept_type)
 const PTYPE_BATCH_SEND: i32 = 3; // Batch sends, no asynchrony
@@ -236,12 +273,15 @@ const OP_DETACH: u32 = 21;
 const OP_TRANSACTION: u32 = 29;
 const OP_COMMIT: u32 = 30;
 const OP_ROLLBACK: u32 = 31;
+const OP_RECONNECT: u32 = 33;
 const OP_OPEN_BLOB: u32 = 35;
 const OP_GET_SEGMENT: u32 = 36;
 const OP_PUT_SEGMENT: u32 = 37;
+const OP_CANCEL_BLOB: u32 = 38;
 const OP_CLOSE_BLOB: u32 = 39;
 const OP_INFO_DATABASE: u32 = 40;
 const OP_INFO_TRANSACTION: u32 = 42;
+const OP_INFO_BLOB: u32 = 43;
 const OP_BATCH_SEGMENTS: u32 = 44;
 const OP_QUE_EVENTS: u32 = 48;
 const OP_CANCEL_EVENTS: u32 = 49;
@@ -281,6 +321,9 @@ const OP_CRYPT: u32 = 96;
 const OP_CRYPT_KEY_CALLBACK: u32 = 97;
 const OP_COND_ACCEPT: u32 = 98;
 
+// op_connect_request type, the auxiliary connection for events
+const P_REQ_ASYNC: u32 = 1;
+
 const CNCT_USER: u8 = 1;
 const CNCT_PASSWD: u8 = 2;
 const CNCT_HOST: u8 = 4;
@@ -349,16 +392,44 @@ const ISC_INFO_TRA_OLDEST_ACTIVE: u32 = 7;
 const ISC_INFO_TRA_ISOLATION: u32 = 8;
 const ISC_INFO_TRA_ACCESS: u32 = 9;
 const ISC_INFO_TRA_LOCK_TIMEOUT: u32 = 10;
+
+// Blob information items
+const ISC_INFO_BLOB_TOTAL_LENGTH: u32 = 6;
+
+// params![a, b] is a &[&dyn ToSqlParam], each value is converted with Param::from()
 #[macro_export]
 macro_rules! params {
     () => {
         &[] as &[&dyn $crate::ToSqlParam]
     };
-    ($($param:expr),*) => {
+    ($($param:expr),+ $(,)?) => {
         &[$(&$crate::Param::from($param) as &dyn $crate::ToSqlParam),*] as &[&dyn $crate::ToSqlParam]
     };
 }
 
+// to_params! { struct S { a: i32, b: String } } declares S and implements ToParams
+// for it, each field is converted with Param::from() in declaration order
+#[macro_export]
+macro_rules! to_params {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $($(#[$fmeta:meta])* $fvis:vis $field:ident : $ftype:ty),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $name {
+            $($(#[$fmeta])* $fvis $field: $ftype),*
+        }
+
+        impl $crate::ToParams for $name {
+            fn to_params(&self) -> ::std::vec::Vec<$crate::Param> {
+                ::std::vec![$($crate::Param::from(::std::clone::Clone::clone(&self.$field))),*]
+            }
+        }
+    };
+}
+
 #[cfg(test)]
 mod test_async;
 #[cfg(test)]
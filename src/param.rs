@@ -20,12 +20,14 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+use super::error::{Error, ParamError};
 use super::*;
 use chrono;
-use chrono::{Datelike, Timelike};
+use chrono::{Datelike, TimeZone, Timelike};
 use chrono_tz;
 use rust_decimal::Decimal;
 use std::io::prelude::*;
+use std::path::{Path, PathBuf};
 
 #[derive(PartialEq, Debug, Clone)]
 pub enum Param {
@@ -42,9 +44,29 @@ pub enum Param {
     Double(f64),
     Blob(Vec<u8>),
     TimeStampTZ(chrono::DateTime<chrono_tz::Tz>),
-    // TimeTz(??),
+    TimeTz((chrono::NaiveTime, chrono_tz::Tz)), // wall clock time in the zone
     Decimal(Decimal),
     Boolean(bool),
+    BlobFile(PathBuf), // streamed into a blob when the statement is executed
+    Scaled(i128, i32), // mantissa * 10^scale, Scaled(12345, -2) is 123.45
+    // Pre-encoded value and its blr, sent as is. The caller is responsible for
+    // the encoding, including the XDR padding of the value to 4 bytes.
+    Raw { value: Vec<u8>, blr: Vec<u8> },
+    DecFloat16(Decimal), // DECFLOAT(16), at most 16 significant digits
+    DecFloat34(Decimal), // DECFLOAT(34)
+    Octets(Vec<u8>),     // inline binary string, for CHAR/VARCHAR CHARACTER SET OCTETS
+    BlobId(BlobId),      // an existing blob, e.g. fetched with set_fetch_blobs(false)
+}
+
+impl Param {
+    // wall clock time of dt in tz, for a TIMESTAMP (WITHOUT TIME ZONE) column
+    pub fn timestamp_in_zone(dt: chrono::DateTime<chrono::Utc>, tz: chrono_tz::Tz) -> Param {
+        Param::TimeStamp(dt.with_timezone(&tz).naive_local())
+    }
+}
+
+pub fn blob_file<P: AsRef<Path>>(path: P) -> Param {
+    Param::BlobFile(path.as_ref().to_path_buf())
 }
 
 impl From<&str> for Param {
@@ -53,6 +75,12 @@ impl From<&str> for Param {
     }
 }
 
+impl From<String> for Param {
+    fn from(v: String) -> Param {
+        Param::Text(v)
+    }
+}
+
 impl From<i16> for Param {
     fn from(v: i16) -> Param {
         Param::Short(v)
@@ -113,12 +141,43 @@ impl From<&[u8]> for Param {
     }
 }
 
+impl From<Vec<u8>> for Param {
+    fn from(v: Vec<u8>) -> Param {
+        Param::Blob(v)
+    }
+}
+
+// None is sent as NULL
+impl<T> From<Option<T>> for Param
+where
+    Param: From<T>,
+{
+    fn from(v: Option<T>) -> Param {
+        match v {
+            Some(v) => Param::from(v),
+            None => Param::Null,
+        }
+    }
+}
+
+impl From<BlobId> for Param {
+    fn from(v: BlobId) -> Param {
+        Param::BlobId(v)
+    }
+}
+
 impl From<chrono::DateTime<chrono_tz::Tz>> for Param {
     fn from(v: chrono::DateTime<chrono_tz::Tz>) -> Param {
         Param::TimeStampTZ(v)
     }
 }
 
+impl From<(chrono::NaiveTime, chrono_tz::Tz)> for Param {
+    fn from(v: (chrono::NaiveTime, chrono_tz::Tz)) -> Param {
+        Param::TimeTz(v)
+    }
+}
+
 impl From<Decimal> for Param {
     fn from(v: Decimal) -> Param {
         Param::Decimal(v)
@@ -131,12 +190,16 @@ impl From<bool> for Param {
     }
 }
 
+// blr of a blob_file() parameter until the statement streams the file,
+// which is bound with its path as the value. Not a valid parameter blr.
+pub(crate) const BLR_BLOB_FILE: &[u8] = &[255];
+
 pub trait ToSqlParam {
-    fn to_value_blr_isnull(&self) -> (Vec<u8>, Vec<u8>, bool);
+    fn to_value_blr_isnull(&self) -> Result<(Vec<u8>, Vec<u8>, bool), Error>;
 }
 
 impl ToSqlParam for Param {
-    fn to_value_blr_isnull(&self) -> (Vec<u8>, Vec<u8>, bool) {
+    fn to_value_blr_isnull(&self) -> Result<(Vec<u8>, Vec<u8>, bool), Error> {
         let mut value: Vec<u8> = Vec::new();
         let mut blr: Vec<u8> = Vec::new();
         let mut isnull = false;
@@ -180,7 +243,7 @@ impl ToSqlParam for Param {
             }
             Param::Date(d) => {
                 value
-                    .write(&utils::convert_date(d.year(), d.month(), d.day()))
+                    .write(&utils::convert_date(d.year(), d.month(), d.day())?)
                     .unwrap();
                 blr.write(&[12]).unwrap();
             }
@@ -188,7 +251,7 @@ impl ToSqlParam for Param {
                 let d = dt.date();
                 let t = dt.time();
                 value
-                    .write(&utils::convert_date(d.year(), d.month(), d.day()))
+                    .write(&utils::convert_date(d.year(), d.month(), d.day())?)
                     .unwrap();
                 value
                     .write(&utils::convert_time(
@@ -213,8 +276,52 @@ impl ToSqlParam for Param {
                 value.write(&v).unwrap();
                 blr.write(&b).unwrap();
             }
-            Param::TimeStampTZ(_dt_tz) => {
-                // TODO:
+            Param::TimeStampTZ(dt) => {
+                // UTC timestamp and the Firebird id of the zone
+                let id = timezone_id(&dt.timezone())?;
+                let utc = dt.naive_utc();
+                let (d, t) = (utc.date(), utc.time());
+                value
+                    .write(&utils::convert_date(d.year(), d.month(), d.day())?)
+                    .unwrap();
+                value
+                    .write(&utils::convert_time(
+                        t.hour(),
+                        t.minute(),
+                        t.second(),
+                        t.nanosecond(),
+                    ))
+                    .unwrap();
+                // XDR short, sign extended
+                value.write(&(id as i16 as i32).to_be_bytes()).unwrap();
+                blr.write(&[29]).unwrap();
+            }
+            Param::TimeTz((t, tz)) => {
+                // UTC time on the same reference day as utils::bytes_to_time_tz()
+                let id = timezone_id(tz)?;
+                let date = utils::time_tz_reference_date();
+                let utc = tz
+                    .from_local_datetime(&chrono::NaiveDateTime::new(date, *t))
+                    .earliest()
+                    .ok_or_else(|| {
+                        Error::ParamError(ParamError::new(&format!(
+                            "time {} doesn't exist in {}",
+                            t,
+                            tz.name()
+                        )))
+                    })?
+                    .naive_utc()
+                    .time();
+                value
+                    .write(&utils::convert_time(
+                        utc.hour(),
+                        utc.minute(),
+                        utc.second(),
+                        utc.nanosecond(),
+                    ))
+                    .unwrap();
+                value.write(&(id as i16 as i32).to_be_bytes()).unwrap();
+                blr.write(&[28]).unwrap();
             }
             Param::Decimal(d) => {
                 let s = d.to_string();
@@ -231,16 +338,85 @@ impl ToSqlParam for Param {
                 }
                 blr.write(&[23]).unwrap();
             }
+            Param::Scaled(n, scale) => {
+                if *scale < -128 || *scale > 127 {
+                    return Err(Error::ParamError(ParamError::new(&format!(
+                        "scale out of range: {}",
+                        scale
+                    ))));
+                }
+                if let Ok(n) = i64::try_from(*n) {
+                    value.write(&utils::bint64_to_bytes(n)).unwrap();
+                    blr.write(&[16, *scale as u8]).unwrap();
+                } else {
+                    value.write(&utils::bint128_to_bytes(*n)).unwrap();
+                    blr.write(&[26, *scale as u8]).unwrap();
+                }
+            }
+            Param::Raw {
+                value: raw_value,
+                blr: raw_blr,
+            } => {
+                value.write(raw_value).unwrap();
+                blr.write(raw_blr).unwrap();
+            }
+            Param::DecFloat16(d) => {
+                value.write(&decfloat::decimal_to_decimal64(d)?).unwrap();
+                blr.write(&[24]).unwrap();
+            }
+            Param::DecFloat34(d) => {
+                value.write(&decfloat::decimal_to_decimal128(d)?).unwrap();
+                blr.write(&[25]).unwrap();
+            }
+            Param::Octets(b) => {
+                // blr_text2 with character set 1 (OCTETS), not a blob
+                let n = b.len();
+                if n > i16::MAX as usize {
+                    return Err(Error::ParamError(ParamError::new(&format!(
+                        "octets of {} bytes are too long (max {})",
+                        n,
+                        i16::MAX
+                    ))));
+                }
+                let (_, v) = utils::bytes_to_blr(b);
+                value.write(&v).unwrap();
+                blr.write(&[15, 1, 0, (n & 255) as u8, (n >> 8) as u8])
+                    .unwrap();
+            }
+            Param::BlobId(id) => {
+                value.write(&id.0).unwrap();
+                blr.write(&[9, 0]).unwrap(); // blr_quad
+            }
+            Param::BlobFile(path) => {
+                let path = path.to_str().ok_or_else(|| {
+                    Error::ParamError(ParamError::new(&format!(
+                        "blob file {} is not a UTF-8 path",
+                        path.display()
+                    )))
+                })?;
+                value.write(path.as_bytes()).unwrap();
+                blr.write(BLR_BLOB_FILE).unwrap();
+            }
         }
-        (value, blr, isnull)
+        Ok((value, blr, isnull))
     }
 }
 
+// Firebird id of a named zone, offsets like +05:30 aren't chrono_tz zones
+fn timezone_id(tz: &chrono_tz::Tz) -> Result<u16, Error> {
+    tz_map::timezone_id_by_name(tz.name()).ok_or_else(|| {
+        Error::ParamError(ParamError::new(&format!(
+            "time zone {} is unknown to Firebird",
+            tz.name()
+        )))
+    })
+}
+
 macro_rules! to_sql_param(
     ($t:ty) => (
         impl ToSqlParam for $t {
             #[inline]
-            fn to_value_blr_isnull(&self) -> (Vec<u8>, Vec<u8>, bool) {
+            fn to_value_blr_isnull(&self) -> Result<(Vec<u8>, Vec<u8>, bool), Error> {
                 Param::from(*self).to_value_blr_isnull()
             }
         }
@@ -258,5 +434,219 @@ to_sql_param!(f32);
 to_sql_param!(f64);
 to_sql_param!(&[u8]);
 to_sql_param!(chrono::DateTime<chrono_tz::Tz>);
+to_sql_param!((chrono::NaiveTime, chrono_tz::Tz));
 to_sql_param!(Decimal);
 to_sql_param!(bool);
+to_sql_param!(BlobId);
+
+// sent as INT128 (or BIGINT) with the scale of the value, no rounding
+#[cfg(feature = "bigdecimal")]
+impl ToSqlParam for bigdecimal::BigDecimal {
+    fn to_value_blr_isnull(&self) -> Result<(Vec<u8>, Vec<u8>, bool), Error> {
+        use bigdecimal::ToPrimitive;
+        let (n, exponent) = self.as_bigint_and_exponent();
+        let n = n.to_i128().ok_or_else(|| {
+            Error::ParamError(ParamError::new(&format!("{} is out of INT128 range", self)))
+        })?;
+        let scale = i32::try_from(-exponent).unwrap_or(i32::MAX);
+        Param::Scaled(n, scale).to_value_blr_isnull()
+    }
+}
+
+#[test]
+fn test_param_date_out_of_range() {
+    let d = chrono::NaiveDate::from_ymd_opt(0, 1, 1).unwrap();
+    assert!(matches!(
+        Param::from(d).to_value_blr_isnull(),
+        Err(Error::ParamError(_))
+    ));
+    assert!(matches!(
+        Param::from(d.and_hms_opt(0, 0, 0).unwrap()).to_value_blr_isnull(),
+        Err(Error::ParamError(_))
+    ));
+    assert!(
+        Param::from(chrono::NaiveDate::from_ymd_opt(1, 1, 1).unwrap())
+            .to_value_blr_isnull()
+            .is_ok()
+    );
+}
+
+#[test]
+fn test_param_blob_file() {
+    let p = blob_file("/tmp/foo.pdf");
+    assert_eq!(p, Param::BlobFile(PathBuf::from("/tmp/foo.pdf")));
+    assert_eq!(
+        p.to_value_blr_isnull().unwrap(),
+        (b"/tmp/foo.pdf".to_vec(), BLR_BLOB_FILE.to_vec(), false)
+    );
+}
+
+#[test]
+fn test_param_scaled() {
+    let (value, blr, isnull) = Param::Scaled(12345, -2).to_value_blr_isnull().unwrap();
+    assert_eq!(value, utils::bint64_to_bytes(12345).to_vec());
+    assert_eq!(blr, vec![16, 254]);
+    assert!(!isnull);
+
+    let (value, blr, _) = Param::Scaled(i128::MAX, -4).to_value_blr_isnull().unwrap();
+    assert_eq!(value, utils::bint128_to_bytes(i128::MAX).to_vec());
+    assert_eq!(blr, vec![26, 252]);
+
+    assert!(Param::Scaled(1, -200).to_value_blr_isnull().is_err());
+}
+
+#[test]
+fn test_param_raw() {
+    let p = Param::Raw {
+        value: vec![0, 0, 0, 1],
+        blr: vec![8, 0],
+    };
+    let (value, blr, isnull) = p.to_value_blr_isnull().unwrap();
+    assert_eq!(value, vec![0, 0, 0, 1]);
+    assert_eq!(blr, vec![8, 0]);
+    assert!(!isnull);
+}
+
+#[test]
+fn test_param_decfloat() {
+    let (value, blr, isnull) = Param::DecFloat16(Decimal::from(1))
+        .to_value_blr_isnull()
+        .unwrap();
+    assert_eq!(value, vec![0x22, 0x38, 0, 0, 0, 0, 0, 0x01]);
+    assert_eq!(blr, vec![24]);
+    assert!(!isnull);
+
+    let (value, blr, _) = Param::DecFloat34(Decimal::from(1))
+        .to_value_blr_isnull()
+        .unwrap();
+    assert_eq!(value.len(), 16);
+    assert_eq!(blr, vec![25]);
+
+    assert!(matches!(
+        Param::DecFloat16(Decimal::MAX).to_value_blr_isnull(),
+        Err(Error::ValueError(_))
+    ));
+}
+
+#[test]
+fn test_param_timestamp_in_zone() {
+    use chrono::TimeZone;
+    let dt = chrono::Utc
+        .with_ymd_and_hms(2024, 1, 15, 12, 30, 0)
+        .unwrap();
+    assert_eq!(
+        Param::timestamp_in_zone(dt, chrono_tz::Asia::Tokyo),
+        Param::TimeStamp(
+            chrono::NaiveDate::from_ymd_opt(2024, 1, 15)
+                .unwrap()
+                .and_hms_opt(21, 30, 0)
+                .unwrap()
+        )
+    );
+    // daylight saving time
+    let dt = chrono::Utc.with_ymd_and_hms(2024, 7, 1, 12, 0, 0).unwrap();
+    assert_eq!(
+        Param::timestamp_in_zone(dt, chrono_tz::Europe::Berlin),
+        Param::TimeStamp(
+            chrono::NaiveDate::from_ymd_opt(2024, 7, 1)
+                .unwrap()
+                .and_hms_opt(14, 0, 0)
+                .unwrap()
+        )
+    );
+}
+
+#[test]
+fn test_param_octets() {
+    let (value, blr, isnull) = Param::Octets(vec![0xde, 0xad, 0xbe, 0xef, 0xff])
+        .to_value_blr_isnull()
+        .unwrap();
+    assert_eq!(value, vec![0xde, 0xad, 0xbe, 0xef, 0xff, 0, 0, 0]);
+    assert_eq!(blr, vec![15, 1, 0, 5, 0]);
+    assert!(!isnull);
+
+    assert!(Param::Octets(vec![0; 32767]).to_value_blr_isnull().is_ok());
+    assert!(matches!(
+        Param::Octets(vec![0; 32768]).to_value_blr_isnull(),
+        Err(Error::ParamError(_))
+    ));
+}
+
+#[test]
+fn test_param_blob_id() {
+    let id = BlobId([0, 0, 0, 1, 0, 0, 0, 2]);
+    let (value, blr, isnull) = Param::from(id).to_value_blr_isnull().unwrap();
+    assert_eq!(value, vec![0, 0, 0, 1, 0, 0, 0, 2]);
+    assert_eq!(blr, vec![9, 0]);
+    assert!(!isnull);
+}
+
+#[cfg(feature = "bigdecimal")]
+#[test]
+fn test_param_bigdecimal() {
+    use std::str::FromStr;
+    let d = bigdecimal::BigDecimal::from_str("1234567890123456789012345678901234.5678").unwrap();
+    assert_eq!(
+        d.to_value_blr_isnull().unwrap(),
+        Param::Scaled(12345678901234567890123456789012345678, -4)
+            .to_value_blr_isnull()
+            .unwrap()
+    );
+    let d = bigdecimal::BigDecimal::from_str("1e-200").unwrap();
+    assert!(d.to_value_blr_isnull().is_err());
+    let d = bigdecimal::BigDecimal::from_str("123456789012345678901234567890123456789012").unwrap();
+    assert!(d.to_value_blr_isnull().is_err());
+}
+
+#[test]
+fn test_param_timestamp_tz() {
+    use chrono::TimeZone;
+    let tz: chrono_tz::Tz = "Asia/Kolkata".parse().unwrap();
+    let dt = tz.with_ymd_and_hms(2024, 2, 29, 23, 45, 1).unwrap();
+    let (value, blr, isnull) = Param::from(dt).to_value_blr_isnull().unwrap();
+    assert_eq!(blr, vec![29]);
+    assert!(!isnull);
+    // 18:15:01 UTC, then Asia/Kolkata (65248)
+    let mut expected = utils::convert_date(2024, 2, 29).unwrap().to_vec();
+    expected.extend(utils::convert_time(18, 15, 1, 0));
+    expected.extend([0xff, 0xff, 0xfe, 0xe0]);
+    assert_eq!(value, expected);
+    assert_eq!(utils::bytes_to_date_time_tz(&value), dt);
+
+    assert_eq!(tz_map::timezone_id_by_name("UTC+05:30"), None);
+}
+
+#[test]
+fn test_param_time_tz() {
+    let tz: chrono_tz::Tz = "Asia/Tokyo".parse().unwrap();
+    let t = chrono::NaiveTime::from_hms_opt(12, 34, 56).unwrap();
+    let (value, blr, _) = Param::from((t, tz)).to_value_blr_isnull().unwrap();
+    assert_eq!(blr, vec![28]);
+    // 03:34:56 UTC, then Asia/Tokyo (65211)
+    let mut expected = utils::convert_time(3, 34, 56, 0).to_vec();
+    expected.extend([0xff, 0xff, 0xfe, 0xbb]);
+    assert_eq!(value, expected);
+    assert_eq!(utils::bytes_to_time_tz(&value), (t, tz));
+}
+
+#[test]
+fn test_param_time_tz_reference_date() {
+    // converted with the offset on 2020-01-01 (CET), whatever the date is today
+    let tz: chrono_tz::Tz = "Europe/Berlin".parse().unwrap();
+    let t = chrono::NaiveTime::from_hms_opt(12, 0, 0).unwrap();
+    let (value, _, _) = Param::from((t, tz)).to_value_blr_isnull().unwrap();
+    assert_eq!(&value[..4], &utils::convert_time(11, 0, 0, 0)[..]);
+}
+
+#[test]
+fn test_param_from_owned_and_option() {
+    assert_eq!(
+        Param::from("abc".to_string()),
+        Param::Text("abc".to_string())
+    );
+    assert_eq!(Param::from(vec![1u8, 2]), Param::Blob(vec![1, 2]));
+    assert_eq!(Param::from(Some(1i32)), Param::Long(1));
+    assert_eq!(Param::from(None::<String>), Param::Null);
+    let (_, _, isnull) = Param::from(None::<i64>).to_value_blr_isnull().unwrap();
+    assert!(isnull);
+}
@@ -19,7 +19,7 @@
 // THE SOFTWARE.
 
 use super::error::Error;
-use super::param::ToSqlParam;
+use super::param::{Param, ToSqlParam};
 use super::statement::Statement;
 use super::statement_async::StatementAsync;
 
@@ -113,3 +113,78 @@ single_tuple_impl!(13: (0 A), (1 B), (2 C), (3 D), (4 E), (5 F), (6 G), (7 H), (
 single_tuple_impl!(14: (0 A), (1 B), (2 C), (3 D), (4 E), (5 F), (6 G), (7 H), (8 I), (9 J), (10 K), (11 L), (12 M), (13 N));
 single_tuple_impl!(15: (0 A), (1 B), (2 C), (3 D), (4 E), (5 F), (6 G), (7 H), (8 I), (9 J), (10 K), (11 L), (12 M), (13 N), (14 O));
 single_tuple_impl!(16: (0 A), (1 B), (2 C), (3 D), (4 E), (5 F), (6 G), (7 H), (8 I), (9 J), (10 K), (11 L), (12 M), (13 N), (14 O), (15 P));
+
+// A record whose fields are bound as parameters in declaration order,
+// see Statement::execute_struct(). Usually implemented with to_params!{}.
+pub trait ToParams {
+    fn to_params(&self) -> Vec<Param>;
+}
+
+// Placeholder list for `IN` with n values, e.g. "(?, ?, ?)".
+// An empty list becomes "(NULL)" which matches nothing.
+pub fn sql_in(n: usize) -> String {
+    if n == 0 {
+        return "(NULL)".to_string();
+    }
+    format!("({})", vec!["?"; n].join(", "))
+}
+
+// ROWS clause for a page of limit rows after skipping offset rows,
+// e.g. sql_rows(20, 10) is "ROWS 21 TO 30".
+pub fn sql_rows(offset: usize, limit: usize) -> String {
+    format!(
+        "ROWS {} TO {}",
+        offset.saturating_add(1),
+        offset.saturating_add(limit)
+    )
+}
+
+#[test]
+fn test_sql_in() {
+    assert_eq!(sql_in(0), "(NULL)");
+    assert_eq!(sql_in(1), "(?)");
+    assert_eq!(sql_in(3), "(?, ?, ?)");
+}
+
+#[test]
+fn test_sql_rows() {
+    assert_eq!(sql_rows(0, 10), "ROWS 1 TO 10");
+    assert_eq!(sql_rows(20, 10), "ROWS 21 TO 30");
+    // no rows
+    assert_eq!(sql_rows(5, 0), "ROWS 6 TO 5");
+}
+
+#[test]
+fn test_params_macro() {
+    fn len(params: &[&dyn ToSqlParam]) -> usize {
+        params.len()
+    }
+    assert_eq!(len(crate::params![]), 0);
+    assert_eq!(len(crate::params![1]), 1);
+    assert_eq!(len(crate::params![1,]), 1);
+    assert_eq!(len(crate::params![1, "a", 1.5]), 3);
+    assert_eq!(len(crate::params![1, "a", 1.5,]), 3);
+}
+
+#[test]
+fn test_to_params_macro() {
+    crate::to_params! {
+        #[derive(Clone)]
+        struct Record {
+            id: i32,
+            pub name: String,
+            note: Option<String>,
+        }
+    }
+    let r = Record {
+        id: 1,
+        name: "a".to_string(),
+        note: None,
+    };
+    assert_eq!(
+        r.to_params(),
+        vec![Param::Long(1), Param::Text("a".to_string()), Param::Null]
+    );
+    // the struct itself is left as declared
+    assert_eq!(r.clone().name, "a");
+}
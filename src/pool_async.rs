@@ -0,0 +1,124 @@
+// MIT License
+//
+// Copyright (c) 2021 Hajime Nakagami<nakagami@gmail.com>
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use std::ops::{Deref, DerefMut};
+use std::sync::Mutex;
+
+use super::connection_async::ConnectionAsync;
+use super::error::Error;
+
+macro_rules! debug_print {
+    //    ($( $args:expr ),*) => { println!( $( $args ),* ); }
+    ($( $args:expr ),*) => {};
+}
+
+pub struct PoolAsync {
+    conn_string: String,
+    max_idle: usize,
+    idle: Mutex<Vec<ConnectionAsync>>,
+}
+
+impl PoolAsync {
+    pub fn new(conn_string: &str, max_idle: usize) -> PoolAsync {
+        PoolAsync {
+            conn_string: conn_string.to_string(),
+            max_idle,
+            idle: Mutex::new(Vec::new()),
+        }
+    }
+
+    // an idle connection which passes is_valid(), or a new one
+    pub async fn get(&self) -> Result<PooledConnectionAsync<'_>, Error> {
+        loop {
+            let conn = self.idle.lock().unwrap().pop();
+            match conn {
+                Some(mut conn) => {
+                    if conn.is_valid().await {
+                        return Ok(PooledConnectionAsync {
+                            pool: self,
+                            conn: Some(conn),
+                        });
+                    }
+                }
+                None => break,
+            }
+        }
+        let conn = ConnectionAsync::connect(&self.conn_string).await?;
+        Ok(PooledConnectionAsync {
+            pool: self,
+            conn: Some(conn),
+        })
+    }
+
+    pub fn idle_count(&self) -> usize {
+        self.idle.lock().unwrap().len()
+    }
+
+    async fn put_back(&self, mut conn: ConnectionAsync) {
+        // don't hand over uncommitted work to the next user
+        if conn.rollback().await.is_err() {
+            return;
+        }
+        let mut idle = self.idle.lock().unwrap();
+        if idle.len() < self.max_idle {
+            idle.push(conn);
+        }
+    }
+}
+
+// returned to the pool with release(), a dropped one is closed instead
+pub struct PooledConnectionAsync<'pool> {
+    pool: &'pool PoolAsync,
+    conn: Option<ConnectionAsync>,
+}
+
+impl PooledConnectionAsync<'_> {
+    // rolls back uncommitted work and keeps the connection for the next get()
+    pub async fn release(mut self) {
+        if let Some(conn) = self.conn.take() {
+            self.pool.put_back(conn).await;
+        }
+    }
+}
+
+impl Deref for PooledConnectionAsync<'_> {
+    type Target = ConnectionAsync;
+
+    fn deref(&self) -> &ConnectionAsync {
+        self.conn.as_ref().unwrap()
+    }
+}
+
+impl DerefMut for PooledConnectionAsync<'_> {
+    fn deref_mut(&mut self) -> &mut ConnectionAsync {
+        self.conn.as_mut().unwrap()
+    }
+}
+
+impl Drop for PooledConnectionAsync<'_> {
+    fn drop(&mut self) {
+        // see Drop for WireProtocolAsync, the connection isn't returned to the pool
+        if self.conn.is_some() {
+            debug_print!("PooledConnectionAsync dropped without release()");
+        }
+    }
+}
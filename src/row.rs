@@ -20,17 +20,99 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 use super::cellvalue::{CellValue, CellValueToVal};
+use super::charset::Charset;
 use super::error::{Error, ValueError};
-use std::collections::VecDeque;
+use super::xsqlvar::*;
+use std::collections::{HashMap, VecDeque};
+use std::io::Write;
 use std::result::Result;
+use std::sync::Arc;
+
+pub struct ResultSetMetaData {
+    columns: Vec<XSQLVar>,
+}
+
+impl ResultSetMetaData {
+    pub(crate) fn new(xsqlda: &[XSQLVar]) -> ResultSetMetaData {
+        ResultSetMetaData {
+            columns: xsqlda.to_vec(),
+        }
+    }
+
+    pub fn column_count(&self) -> usize {
+        self.columns.len()
+    }
+
+    // alias name
+    pub fn column_name(&self, idx: usize) -> Option<&str> {
+        self.columns.get(idx).map(|x| x.aliasname.as_str())
+    }
+
+    // SQL type name like "INTEGER", "VARCHAR"
+    pub fn column_type(&self, idx: usize) -> Option<&'static str> {
+        self.columns.get(idx).map(|x| x.type_name())
+    }
+
+    // None for a type unknown to this crate
+    pub fn sql_type(&self, idx: usize) -> Option<SqlType> {
+        self.columns
+            .get(idx)
+            .and_then(|x| SqlType::from_u32(x.sqltype))
+    }
+
+    pub fn is_nullable(&self, idx: usize) -> Option<bool> {
+        self.columns.get(idx).map(|x| x.null_ok)
+    }
+
+    // number of digits, or length in bytes for CHAR/VARCHAR
+    pub fn precision(&self, idx: usize) -> Option<i32> {
+        self.columns.get(idx).map(|x| x.precision())
+    }
+
+    pub fn scale(&self, idx: usize) -> Option<i32> {
+        self.columns.get(idx).map(|x| -x.sqlscale)
+    }
+}
 
 pub struct Rows {
     rows: VecDeque<Vec<CellValue>>,
+    metadata: Arc<ResultSetMetaData>,
 }
 
 impl Rows {
-    pub(crate) fn new(rows: VecDeque<Vec<CellValue>>) -> Rows {
-        Rows { rows }
+    pub(crate) fn new(rows: VecDeque<Vec<CellValue>>, metadata: ResultSetMetaData) -> Rows {
+        Rows {
+            rows,
+            metadata: Arc::new(metadata),
+        }
+    }
+
+    pub fn metadata(&self) -> &ResultSetMetaData {
+        &self.metadata
+    }
+
+    // RFC 4180 CSV, NULL is an empty field
+    pub fn write_csv<W: Write>(self, w: &mut W, header: bool) -> Result<(), Error> {
+        self.write_delimited(w, ',', header)
+    }
+
+    pub fn write_tsv<W: Write>(self, w: &mut W, header: bool) -> Result<(), Error> {
+        self.write_delimited(w, '\t', header)
+    }
+
+    fn write_delimited<W: Write>(
+        self,
+        w: &mut W,
+        delimiter: char,
+        header: bool,
+    ) -> Result<(), Error> {
+        if header {
+            write_delimited_header(w, &self.metadata, delimiter)?;
+        }
+        for row in self {
+            write_delimited_row(w, &row, delimiter)?;
+        }
+        Ok(())
     }
 
     pub fn mapped<F, B>(self, f: F) -> MappedRows<F>
@@ -46,17 +128,91 @@ impl Iterator for Rows {
 
     fn next(&mut self) -> Option<Row> {
         match self.rows.pop_front() {
-            Some(row) => Some(Row { row: row }),
+            Some(row) => Some(Row {
+                row,
+                metadata: self.metadata.clone(),
+            }),
             None => None,
         }
     }
 }
 
+pub struct RowResults {
+    rows: Rows,
+    error: Option<Error>,
+}
+
+impl RowResults {
+    pub(crate) fn new(rows: Rows, error: Option<Error>) -> RowResults {
+        RowResults { rows, error }
+    }
+
+    pub fn metadata(&self) -> &ResultSetMetaData {
+        self.rows.metadata()
+    }
+}
+
+impl Iterator for RowResults {
+    type Item = Result<Row, Error>;
+
+    fn next(&mut self) -> Option<Result<Row, Error>> {
+        match self.rows.next() {
+            Some(row) => Some(Ok(row)),
+            None => self.error.take().map(Err),
+        }
+    }
+}
+
+// a line of the column names, shared with the lazy rows of the statements
+pub(crate) fn write_delimited_header<W: Write>(
+    w: &mut W,
+    metadata: &ResultSetMetaData,
+    delimiter: char,
+) -> Result<(), Error> {
+    let names: Vec<String> = metadata
+        .columns
+        .iter()
+        .map(|x| quote_field(&x.aliasname, delimiter))
+        .collect();
+    write!(w, "{}\r\n", names.join(&delimiter.to_string()))?;
+    Ok(())
+}
+
+pub(crate) fn write_delimited_row<W: Write>(
+    w: &mut W,
+    row: &Row,
+    delimiter: char,
+) -> Result<(), Error> {
+    let fields: Vec<String> = row
+        .row
+        .iter()
+        .map(|v| match v {
+            CellValue::Null => "".to_string(),
+            _ => quote_field(&v.to_string(), delimiter),
+        })
+        .collect();
+    write!(w, "{}\r\n", fields.join(&delimiter.to_string()))?;
+    Ok(())
+}
+
+fn quote_field(s: &str, delimiter: char) -> String {
+    if s.contains([delimiter, '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
 pub struct Row {
     row: Vec<CellValue>,
+    metadata: Arc<ResultSetMetaData>,
 }
 
 impl Row {
+    pub(crate) fn new(row: Vec<CellValue>, metadata: Arc<ResultSetMetaData>) -> Row {
+        Row { row, metadata }
+    }
+
     pub fn get<T>(&self, idx: usize) -> Result<T, Error>
     where
         CellValue: CellValueToVal<T>,
@@ -69,6 +225,36 @@ impl Row {
             )))
         }
     }
+
+    // a fetched blob decoded with charset, get::<String>() would decode it as
+    // UTF8 and replace what isn't valid
+    pub fn get_blob_string(&self, idx: usize, charset: Charset) -> Result<String, Error> {
+        match self.row.get(idx) {
+            Some(CellValue::BlobText(v)) | Some(CellValue::BlobBinary(v)) => charset.decode(v),
+            Some(_) => Err(Error::ValueError(ValueError::new(
+                "This column isn't a fetched blob",
+            ))),
+            None => Err(Error::ValueError(ValueError::new(
+                "This index doesn't exists",
+            ))),
+        }
+    }
+
+    // column alias -> value, a clashing alias gets "_2", "_3", ... appended
+    pub fn to_map(&self) -> HashMap<String, CellValue> {
+        let mut map = HashMap::with_capacity(self.row.len());
+        for (x, value) in self.metadata.columns.iter().zip(self.row.iter()) {
+            let name = &x.aliasname;
+            let mut key = name.clone();
+            let mut n = 1;
+            while map.contains_key(&key) {
+                n += 1;
+                key = format!("{}_{}", name, n);
+            }
+            map.insert(key, value.clone());
+        }
+        map
+    }
 }
 
 pub struct MappedRows<F> {
@@ -91,3 +277,171 @@ where
         }
     }
 }
+
+#[cfg(test)]
+fn test_xsqlvar(aliasname: &str, sqltype: u32) -> XSQLVar {
+    let mut x = XSQLVar::new();
+    x.aliasname = aliasname.to_string();
+    x.sqltype = sqltype;
+    x
+}
+
+#[test]
+fn test_mapped_rows_error() {
+    let mut rows = VecDeque::new();
+    rows.push_back(vec![
+        CellValue::Long(1),
+        CellValue::Varying("a".to_string()),
+    ]);
+    rows.push_back(vec![CellValue::Long(2), CellValue::Null]);
+
+    // A closure can propagate Row::get() errors with `?`.
+    let xsqlda = [
+        test_xsqlvar("A", SQL_TYPE_LONG),
+        test_xsqlvar("B", SQL_TYPE_VARYING),
+    ];
+    let mut mapped = Rows::new(rows, ResultSetMetaData::new(&xsqlda)).mapped(|row| {
+        let a: i32 = row.get(0)?;
+        let b: String = row.get(1)?;
+        Ok((a, b))
+    });
+
+    assert_eq!(mapped.next().unwrap().unwrap(), (1, "a".to_string()));
+    match mapped.next() {
+        Some(Err(Error::ValueError(e))) => assert_eq!(e.message, "Can't convert string"),
+        _ => panic!("type mismatch must be surfaced as Some(Err(..))"),
+    }
+    assert!(mapped.next().is_none());
+}
+
+#[test]
+fn test_row_to_map() {
+    let mut rows = VecDeque::new();
+    rows.push_back(vec![
+        CellValue::Long(1),
+        CellValue::Varying("a".to_string()),
+        CellValue::Null,
+        CellValue::Long(2),
+    ]);
+    let xsqlda = [
+        test_xsqlvar("A", SQL_TYPE_LONG),
+        test_xsqlvar("B", SQL_TYPE_VARYING),
+        test_xsqlvar("A", SQL_TYPE_LONG),
+        test_xsqlvar("A_2", SQL_TYPE_LONG),
+    ];
+    let mut rows = Rows::new(rows, ResultSetMetaData::new(&xsqlda));
+
+    let map = rows.next().unwrap().to_map();
+    assert_eq!(map.len(), 4);
+    assert_eq!(map["A"], CellValue::Long(1));
+    assert_eq!(map["B"], CellValue::Varying("a".to_string()));
+    assert_eq!(map["A_2"], CellValue::Null);
+    assert_eq!(map["A_2_2"], CellValue::Long(2));
+}
+
+#[test]
+fn test_result_set_metadata() {
+    let mut amount = test_xsqlvar("AMOUNT", SQL_TYPE_INT64);
+    amount.sqlscale = -2;
+    let mut name = test_xsqlvar("NAME", SQL_TYPE_VARYING);
+    name.sqllen = 30;
+    name.null_ok = true;
+    let metadata = ResultSetMetaData::new(&[amount, name]);
+
+    assert_eq!(metadata.column_count(), 2);
+    assert_eq!(metadata.column_name(0), Some("AMOUNT"));
+    assert_eq!(metadata.column_type(0), Some("NUMERIC"));
+    assert_eq!(metadata.precision(0), Some(18));
+    assert_eq!(metadata.scale(0), Some(2));
+    assert_eq!(metadata.is_nullable(0), Some(false));
+    assert_eq!(metadata.column_type(1), Some("VARCHAR"));
+    assert_eq!(metadata.precision(1), Some(30));
+    assert_eq!(metadata.scale(1), Some(0));
+    assert_eq!(metadata.is_nullable(1), Some(true));
+    assert_eq!(metadata.sql_type(0), Some(SqlType::Int64));
+    assert_eq!(metadata.sql_type(1), Some(SqlType::Varying));
+    assert_eq!(metadata.column_name(2), None);
+    assert_eq!(metadata.sql_type(2), None);
+
+    let mut price = test_xsqlvar("PRICE", SQL_TYPE_LONG);
+    price.sqlsubtype = 2;
+    price.sqlscale = -3;
+    let mut qty = test_xsqlvar("QTY", SQL_TYPE_SHORT);
+    qty.sqlsubtype = 1;
+    let count = test_xsqlvar("CNT", SQL_TYPE_INT64);
+    let metadata = ResultSetMetaData::new(&[price, qty, count]);
+    assert_eq!(metadata.column_type(0), Some("DECIMAL"));
+    assert_eq!(metadata.precision(0), Some(9));
+    assert_eq!(metadata.scale(0), Some(3));
+    assert_eq!(metadata.column_type(1), Some("NUMERIC"));
+    assert_eq!(metadata.precision(1), Some(4));
+    assert_eq!(metadata.scale(1), Some(0));
+    assert_eq!(metadata.column_type(2), Some("BIGINT"));
+}
+
+#[test]
+fn test_write_csv() {
+    let mut rows = VecDeque::new();
+    rows.push_back(vec![
+        CellValue::Long(1),
+        CellValue::Varying("a,\"b\"".to_string()),
+    ]);
+    rows.push_back(vec![CellValue::Long(2), CellValue::Null]);
+    let xsqlda = [
+        test_xsqlvar("A", SQL_TYPE_LONG),
+        test_xsqlvar("B", SQL_TYPE_VARYING),
+    ];
+
+    let mut buf: Vec<u8> = Vec::new();
+    Rows::new(rows.clone(), ResultSetMetaData::new(&xsqlda))
+        .write_csv(&mut buf, true)
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(buf).unwrap(),
+        "A,B\r\n1,\"a,\"\"b\"\"\"\r\n2,\r\n"
+    );
+
+    let mut buf: Vec<u8> = Vec::new();
+    Rows::new(rows, ResultSetMetaData::new(&xsqlda))
+        .write_tsv(&mut buf, false)
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(buf).unwrap(),
+        "1\t\"a,\"\"b\"\"\"\r\n2\t\r\n"
+    );
+}
+
+#[test]
+fn test_row_results() {
+    let mut rows = VecDeque::new();
+    rows.push_back(vec![CellValue::Long(1)]);
+    rows.push_back(vec![CellValue::Long(2)]);
+    let metadata = ResultSetMetaData::new(&[test_xsqlvar("A", SQL_TYPE_LONG)]);
+    let error = Error::ValueError(ValueError::new("column 0 (A): can't parse result value"));
+
+    let mut results = RowResults::new(Rows::new(rows, metadata), Some(error));
+    assert_eq!(results.next().unwrap().unwrap().get::<i32>(0).unwrap(), 1);
+    assert_eq!(results.next().unwrap().unwrap().get::<i32>(0).unwrap(), 2);
+    assert!(results.next().unwrap().is_err());
+    assert!(results.next().is_none());
+}
+
+#[test]
+fn test_get_blob_string() {
+    let metadata = Arc::new(ResultSetMetaData::new(&[
+        test_xsqlvar("A", SQL_TYPE_BLOB),
+        test_xsqlvar("B", SQL_TYPE_LONG),
+    ]));
+    let row = Row {
+        row: vec![
+            CellValue::BlobText(b"caf\xe9 \x80".to_vec()),
+            CellValue::Long(1),
+        ],
+        metadata,
+    };
+    assert_eq!(row.get_blob_string(0, Charset::Win1252).unwrap(), "café €");
+    assert_eq!(row.get::<String>(0).unwrap(), "caf\u{fffd} \u{fffd}");
+    assert!(row.get_blob_string(0, Charset::Utf8).is_err());
+    assert!(row.get_blob_string(1, Charset::Win1252).is_err());
+    assert!(row.get_blob_string(2, Charset::Win1252).is_err());
+}
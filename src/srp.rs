@@ -37,9 +37,11 @@ use super::*;
 const SRP_KEY_SIZE: usize = 128;
 const SRP_SALT_SIZE: usize = 32;
 
-const SRP_DEBUG: bool = true;
+// fixed key and salt, only for reproducible test runs
+#[cfg(test)]
 const DEBUG_PRIVATE_KEY: &'static [u8; 64] =
     b"60975527035CF2AD1989806F0407210BC81EDC04E2762A56AFD529DDDA2D4393";
+#[cfg(test)]
 const DEBUG_SALT: &str = "02E268803000000079A478A700000002D1A6979000000026E1601C000000054F";
 
 fn pad(v: &BigInt) -> Vec<u8> {
@@ -73,6 +75,18 @@ pub fn get_string_hash(s: &str) -> BigInt {
     utils::big_int_from_hex_string(&hasher.result_str().as_bytes())
 }
 
+// Login name as the server stores it, the same folding as the fbclient:
+// a "quoted" name is taken as is (with "" for a double quote), otherwise
+// only ASCII letters are uppercased. Non-ASCII characters are kept untouched,
+// so "élodie" becomes "éLODIE".
+pub fn normalize_user(user: &str) -> String {
+    if user.len() >= 2 && user.starts_with('"') && user.ends_with('"') {
+        user[1..user.len() - 1].replace("\"\"", "\"")
+    } else {
+        user.to_ascii_uppercase()
+    }
+}
+
 pub fn get_user_hash(salt: &[u8], user: &str, password: &str) -> BigInt {
     let mut hash1 = Sha1::new();
     hash1.input(user.as_bytes());
@@ -84,24 +98,38 @@ pub fn get_user_hash(salt: &[u8], user: &str, password: &str) -> BigInt {
     utils::bytes_to_big_int(&hex::decode(hash2.result_str()).unwrap())
 }
 
+// SRP_KEY_SIZE random bytes, as large as the prime
+fn random_private_key() -> BigInt {
+    let (prime, _, _) = get_prime();
+    let buf: Vec<u8> = (0..SRP_KEY_SIZE).map(|_| random()).collect();
+    utils::bytes_to_big_int(&buf) % &prime
+}
+
+#[cfg(not(test))]
+fn get_private_key() -> BigInt {
+    random_private_key()
+}
+
+#[cfg(test)]
+fn get_private_key() -> BigInt {
+    utils::big_int_from_hex_string(DEBUG_PRIVATE_KEY)
+}
+
 pub fn get_client_seed() -> (BigInt, BigInt) {
     let (prime, g, _) = get_prime();
-    let key_private_a: BigInt;
-    if SRP_DEBUG {
-        key_private_a = utils::big_int_from_hex_string(DEBUG_PRIVATE_KEY);
-    } else {
-        let r: u128 = random();
-        key_private_a = BigInt::from(r);
-    }
+    let key_private_a = get_private_key();
 
     let key_public_a = g.modpow(&key_private_a, &prime);
     (key_public_a, key_private_a)
 }
 
+#[cfg(test)]
+pub fn get_salt() -> Vec<u8> {
+    hex::decode(DEBUG_SALT).unwrap()
+}
+
+#[cfg(not(test))]
 pub fn get_salt() -> Vec<u8> {
-    if SRP_DEBUG {
-        return hex::decode(DEBUG_SALT).unwrap();
-    }
     let mut buf: Vec<u8> = Vec::new();
     for _ in 0..SRP_SALT_SIZE {
         buf.push(random());
@@ -118,14 +146,7 @@ pub fn get_verifier(user: &str, password: &str, salt: &Vec<u8>) -> BigInt {
 
 pub fn get_server_seed(v: &BigInt) -> (BigInt, BigInt) {
     let (prime, g, k) = get_prime();
-    let key_private_b: BigInt;
-
-    if SRP_DEBUG {
-        key_private_b = utils::big_int_from_hex_string(DEBUG_PRIVATE_KEY);
-    } else {
-        let r: u128 = random();
-        key_private_b = BigInt::from(r);
-    }
+    let key_private_b = get_private_key();
 
     let gb = g.modpow(&key_private_b, &prime); // gb = pow(g, b, N)
     let kv = (&k * v) % &prime; // kv = (k * v) % N
@@ -291,3 +312,23 @@ fn test_srp() {
     );
     assert_eq!(&server_key, &client_key);
 }
+
+#[test]
+fn test_random_private_key() {
+    let a = random_private_key();
+    let b = random_private_key();
+    assert_ne!(a, b);
+    // far beyond a u128
+    assert!(a.bits() > 512);
+    assert!(a < get_prime().0);
+}
+
+#[test]
+fn test_normalize_user() {
+    assert_eq!(normalize_user("sysdba"), "SYSDBA");
+    assert_eq!(normalize_user("élodie"), "éLODIE");
+    assert_eq!(normalize_user("straße"), "STRAßE");
+    assert_eq!(normalize_user("\"MixedCase\""), "MixedCase");
+    assert_eq!(normalize_user("\"a\"\"b\""), "a\"b");
+    assert_eq!(normalize_user("\""), "\"");
+}
@@ -20,22 +20,30 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 #![allow(dead_code)]
-use super::cellvalue::CellValue;
-use super::param::ToSqlParam;
+use super::blob_reader_async::BlobReaderAsync;
+use super::cellvalue::{BlobId, CellValue};
+use super::error::{ParamError, ValueError};
+use super::param::{ToSqlParam, BLR_BLOB_FILE};
 use super::params::Params;
-use super::row::{MappedRows, Row, Rows};
+use super::row::{self, MappedRows, ResultSetMetaData, Row, RowResults, Rows};
 use super::xsqlvar::*;
 use super::ConnectionAsync;
 use super::Error;
 use super::*;
 
-use async_std::task;
-use maplit::hashmap;
 use std::collections::VecDeque;
+use std::fs::File;
+use std::io::Write;
+use std::sync::Arc;
 
 const DSQL_CLOSE: i32 = 1;
 const DSQL_DROP: i32 = 2;
 
+macro_rules! debug_print {
+    //    ($( $args:expr ),*) => { println!( $( $args ),* ); }
+    ($( $args:expr ),*) => {};
+}
+
 pub struct StatementAsync<'conn> {
     conn: &'conn ConnectionAsync,
     pub(crate) trans_handle: i32,
@@ -44,9 +52,16 @@ pub struct StatementAsync<'conn> {
     pub(crate) xsqlda: Vec<XSQLVar>,
     autocommit: bool,
     params: Vec<(Vec<u8>, Vec<u8>, bool)>,
+    fetch_blobs: bool,  // false: blob columns are left as blob ids
+    char_padding: bool, // true: CHAR values keep the padding up to the declared length
+    blob_inline_threshold: Option<usize>, // longer blobs are left as CellValue::BlobId
+    max_rows: Option<usize>, // query() stops fetching after max_rows rows
+    rows_truncated: bool,
+    cursor_open: bool,
+    closed: bool,
 }
 
-impl StatementAsync<'_> {
+impl<'conn> StatementAsync<'conn> {
     pub(super) fn new(
         conn: &ConnectionAsync,
         trans_handle: i32,
@@ -63,14 +78,100 @@ impl StatementAsync<'_> {
             xsqlda,
             autocommit,
             params: Vec::new(),
+            fetch_blobs: true,
+            char_padding: false,
+            blob_inline_threshold: None,
+            max_rows: None,
+            rows_truncated: false,
+            cursor_open: false,
+            closed: false,
         }
     }
 
+    pub fn metadata(&self) -> ResultSetMetaData {
+        ResultSetMetaData::new(&self.xsqlda)
+    }
+
+    // the describe is parsed at prepare, before the statement is executed
+    pub fn column_count(&self) -> usize {
+        self.xsqlda.len()
+    }
+
+    pub fn column_names(&self) -> Vec<&str> {
+        self.xsqlda.iter().map(|x| x.aliasname.as_str()).collect()
+    }
+
+    // isc_info_sql_stmt_* value reported by the server at prepare
+    pub fn statement_type(&self) -> u32 {
+        self.stmt_type
+    }
+
+    pub fn is_select(&self) -> bool {
+        self.stmt_type == ISC_INFO_SQL_STMT_SELECT
+    }
+
+    pub fn is_ddl(&self) -> bool {
+        self.stmt_type == ISC_INFO_SQL_STMT_DDL
+    }
+
+    // statement type is reported by the server, SELECT ... FOR UPDATE is not read only
+    pub fn assert_read_only(&self) -> Result<(), Error> {
+        if self.is_select() {
+            Ok(())
+        } else {
+            Err(Error::ValueError(ValueError::new(&format!(
+                "statement is not read only: statement type {}",
+                self.stmt_type
+            ))))
+        }
+    }
+
+    // false: blob columns are returned as CellValue::BlobId, read with get_blob()
+    pub fn set_fetch_blobs(&mut self, fetch_blobs: bool) {
+        self.fetch_blobs = fetch_blobs;
+    }
+
+    // CHAR values are right trimmed by default, with char_padding they are
+    // padded to the declared character length of the column
+    pub fn set_char_padding(&mut self, char_padding: bool) {
+        self.char_padding = char_padding;
+    }
+
+    // blobs up to n bytes are read in the fetch, longer ones are returned as
+    // CellValue::BlobId to be read with get_blob() when needed
+    pub fn set_blob_inline_threshold(&mut self, n: usize) {
+        self.blob_inline_threshold = Some(n);
+    }
+
+    // query() returns at most n rows, the rest of the result set isn't fetched
+    pub fn set_max_rows(&mut self, n: usize) {
+        self.max_rows = Some(n);
+    }
+
+    // whether the last query() stopped at max_rows while more rows may exist
+    pub fn rows_truncated(&self) -> bool {
+        self.rows_truncated
+    }
+
+    // content of a blob id fetched with set_fetch_blobs(false)
+    pub async fn get_blob(&self, blob_id: &BlobId) -> Result<Vec<u8>, Error> {
+        self.conn
+            ._get_blob_segments(blob_id, self.trans_handle)
+            .await
+    }
+
+    // streams a blob id instead of reading it whole like get_blob()
+    pub async fn blob_reader(&self, blob_id: &BlobId) -> Result<BlobReaderAsync<'_>, Error> {
+        let blob_handle = self.conn._open_blob(blob_id, self.trans_handle).await?;
+        Ok(BlobReaderAsync::new(self.conn, blob_handle))
+    }
+
     #[inline]
     pub(crate) fn bind_parameters(&mut self, params: &[&dyn ToSqlParam]) -> Result<(), Error> {
         self.params = Vec::new();
         for p in params.iter() {
-            self.params.push(p.to_value_blr_isnull());
+            let v = p.to_value_blr_isnull()?;
+            self.params.push(v);
         }
         Ok(())
     }
@@ -81,46 +182,157 @@ impl StatementAsync<'_> {
     }
 
     pub(crate) fn put_parameter<T: ToSqlParam>(&mut self, param: T) -> Result<(), Error> {
-        self.params.push(param.to_value_blr_isnull());
+        let v = param.to_value_blr_isnull()?;
+        self.params.push(v);
+        Ok(())
+    }
+
+    async fn bind<P: Params>(&mut self, params: P) -> Result<(), Error> {
+        // a LazyRowsAsync dropped without close() leaves its cursor open
+        self.close_cursor().await;
+        params.__bind_in_async(self)?;
+        self.create_blob_files().await
+    }
+
+    // blob_file() parameters are bound as their path, stream the files into blobs
+    async fn create_blob_files(&mut self) -> Result<(), Error> {
+        for i in 0..self.params.len() {
+            if self.params[i].1 != BLR_BLOB_FILE {
+                continue;
+            }
+            let path = String::from_utf8_lossy(&self.params[i].0).into_owned();
+            let mut f = File::open(&path)
+                .map_err(|e| ParamError::new(&format!("can't open blob file {}: {}", path, e)))?;
+            let blob_id = self.conn._create_blob(&mut f, self.trans_handle).await?;
+            self.params[i] = (blob_id.0.to_vec(), vec![9, 0], false); // blr_quad
+        }
         Ok(())
     }
 
-    async fn fetch_records(&self, trans_handle: i32) -> Result<VecDeque<Vec<CellValue>>, Error> {
+    // rows fetched before an error are kept
+    async fn fetch_records(
+        &mut self,
+        trans_handle: i32,
+    ) -> Result<(VecDeque<Vec<CellValue>>, Option<Error>), Error> {
         let mut rows = VecDeque::new();
-        let blr = self.calc_blr();
+        let blr = calc_blr(&self.xsqlda)?;
 
+        let mut error: Option<Error> = None;
+        self.rows_truncated = false;
         loop {
-            let (rows_segment, more_data) = self
-                .conn
-                ._fetch(self.stmt_handle, &blr, &self.xsqlda)
-                .await?;
-            rows.extend(rows_segment);
-            if !more_data {
-                break;
+            match self.conn._fetch(self.stmt_handle, &blr, &self.xsqlda).await {
+                Ok((rows_segment, status, e)) => {
+                    rows.extend(rows_segment);
+                    if e.is_some() {
+                        error = e;
+                        break;
+                    }
+                    if let Some(max_rows) = self.max_rows {
+                        if rows.len() > max_rows || (rows.len() == max_rows && status == FETCH_OK) {
+                            rows.truncate(max_rows);
+                            self.rows_truncated = true;
+                            break;
+                        }
+                    }
+                    if status != FETCH_OK {
+                        break;
+                    }
+                }
+                // the connection is broken, the blobs of the rows can't be read
+                Err(Error::IoError(e)) => return Err(Error::IoError(e)),
+                Err(e) => {
+                    error = Some(e);
+                    break;
+                }
             }
         }
 
-        for row in rows.iter_mut() {
-            for cell in row.iter_mut() {
-                match cell {
-                    CellValue::BlobBinary(blob_id) => {
-                        let blob = self.conn._get_blob_segments(&blob_id, trans_handle).await;
-                        *cell = CellValue::BlobBinary(blob.unwrap());
+        // a malformed row comes before the rows after which the fetch stopped
+        let error = self.convert_rows(&mut rows, trans_handle).await?.or(error);
+
+        Ok((rows, error))
+    }
+
+    // CHAR padding, and blob ids read or kept as set on the statement.
+    // Rows from the first one with a malformed blob id on are dropped and the
+    // error is returned to be reported after the rows before it
+    async fn convert_rows(
+        &self,
+        rows: &mut VecDeque<Vec<CellValue>>,
+        trans_handle: i32,
+    ) -> Result<Option<Error>, Error> {
+        if self.char_padding {
+            for row in rows.iter_mut() {
+                for (x, cell) in self.xsqlda.iter().zip(row.iter_mut()) {
+                    if let CellValue::Text(s) = cell {
+                        let len = x.char_length() as usize;
+                        let n = s.chars().count();
+                        if n < len {
+                            s.push_str(&" ".repeat(len - n));
+                        }
                     }
-                    CellValue::BlobText(blob_id) => {
-                        let blob = self.conn._get_blob_segments(&blob_id, trans_handle).await;
-                        *cell = CellValue::BlobText(blob.unwrap());
+                }
+            }
+        }
+
+        for i in 0..rows.len() {
+            for cell in rows[i].iter_mut() {
+                let (blob_id, is_text) = match cell {
+                    CellValue::BlobBinary(blob_id) => (BlobId::from_slice(blob_id), false),
+                    CellValue::BlobText(blob_id) => (BlobId::from_slice(blob_id), true),
+                    _ => continue,
+                };
+                let blob_id = match blob_id {
+                    Ok(blob_id) => blob_id,
+                    Err(e) => {
+                        rows.truncate(i);
+                        return Ok(Some(e.into()));
                     }
-                    _ => {}
+                };
+                if !self.fetch_blobs {
+                    *cell = CellValue::BlobId(blob_id);
+                    continue;
                 }
+                let blob = match self.blob_inline_threshold {
+                    Some(n) => {
+                        self.conn
+                            ._get_blob_segments_limited(&blob_id, trans_handle, n)
+                            .await?
+                    }
+                    None => Some(self.conn._get_blob_segments(&blob_id, trans_handle).await?),
+                };
+                *cell = match blob {
+                    Some(blob) if is_text => CellValue::BlobText(blob),
+                    Some(blob) => CellValue::BlobBinary(blob),
+                    None => CellValue::BlobId(blob_id),
+                };
             }
         }
 
-        Ok(rows)
+        Ok(None)
     }
 
-    pub async fn query<P: Params>(&mut self, params: P) -> Result<Rows, Error> {
-        params.__bind_in_async(self)?;
+    // release the server side cursor, the statement stays prepared
+    pub async fn close_cursor(&mut self) {
+        if self.cursor_open {
+            self.conn
+                ._free_statement(self.stmt_handle, DSQL_CLOSE)
+                .await;
+            self.cursor_open = false;
+        }
+    }
+
+    // free the server side statement, a dropped one is freed with the next request
+    pub async fn close(mut self) {
+        self.closed = true;
+        self.conn._free_statement(self.stmt_handle, DSQL_DROP).await;
+    }
+
+    async fn query_rows<P: Params>(
+        &mut self,
+        params: P,
+    ) -> Result<(VecDeque<Vec<CellValue>>, Option<Error>), Error> {
+        self.bind(params).await?;
         self.conn
             ._execute_statement(
                 self.trans_handle,
@@ -130,17 +342,63 @@ impl StatementAsync<'_> {
             )
             .await?;
         let mut rows: VecDeque<Vec<CellValue>> = VecDeque::new();
+        let mut error: Option<Error> = None;
         if self.stmt_type == ISC_INFO_SQL_STMT_SELECT {
-            rows = self.fetch_records(self.trans_handle).await?;
-            self.conn
-                ._free_statement(self.stmt_handle, DSQL_CLOSE)
-                .await;
+            self.cursor_open = true;
+            (rows, error) = self.fetch_records(self.trans_handle).await?;
+            self.close_cursor().await;
         } else if self.autocommit {
             // commit automatically
             self.conn.commit().await?;
         }
 
-        Ok(Rows::new(rows))
+        Ok((rows, error))
+    }
+
+    pub async fn query<P: Params>(&mut self, params: P) -> Result<Rows, Error> {
+        match self.query_rows(params).await? {
+            (_, Some(e)) => Err(e),
+            (rows, None) => Ok(Rows::new(rows, self.metadata())),
+        }
+    }
+
+    // rows fetched before an error, then the error as the last item
+    pub async fn query_partial<P: Params>(&mut self, params: P) -> Result<RowResults, Error> {
+        let (rows, error) = self.query_rows(params).await?;
+        Ok(RowResults::new(Rows::new(rows, self.metadata()), error))
+    }
+
+    // rows are fetched a batch at a time as next() is called instead of all
+    // at once, the cursor is closed at the end or when the rows are dropped
+    pub async fn query_lazy<P: Params>(
+        &mut self,
+        params: P,
+    ) -> Result<LazyRowsAsync<'_, 'conn>, Error> {
+        let blr = calc_blr(&self.xsqlda)?;
+        self.bind(params).await?;
+        self.conn
+            ._execute_statement(
+                self.trans_handle,
+                self.stmt_handle,
+                self.stmt_type,
+                self.params.as_slice(),
+            )
+            .await?;
+        let is_select = self.stmt_type == ISC_INFO_SQL_STMT_SELECT;
+        if is_select {
+            self.cursor_open = true;
+        } else if self.autocommit {
+            self.conn.commit().await?;
+        }
+        let metadata = Arc::new(self.metadata());
+        Ok(LazyRowsAsync {
+            stmt: self,
+            blr,
+            rows: VecDeque::new(),
+            more_data: is_select,
+            error: None,
+            metadata,
+        })
     }
 
     pub async fn query_map<T, P, F>(&mut self, params: P, f: F) -> Result<MappedRows<F>, Error>
@@ -151,50 +409,231 @@ impl StatementAsync<'_> {
         self.query(params).await.map(|rows| rows.mapped(f))
     }
 
-    pub async fn execute<P: Params>(&mut self, params: P) -> Result<(), Error> {
-        self.query(params).await?;
-        Ok(())
+    // affected row count, without building Rows. A SELECT is still fetched to
+    // the end (errors may come with the rows) and its row count returned
+    pub async fn execute<P: Params>(&mut self, params: P) -> Result<usize, Error> {
+        if self.stmt_type == ISC_INFO_SQL_STMT_SELECT {
+            return match self.query_rows(params).await? {
+                (_, Some(e)) => Err(e),
+                (rows, None) => Ok(rows.len()),
+            };
+        }
+        self.bind(params).await?;
+        let rowcount = self
+            .conn
+            ._execute_statement(
+                self.trans_handle,
+                self.stmt_handle,
+                self.stmt_type,
+                self.params.as_slice(),
+            )
+            .await?;
+        if self.autocommit {
+            self.conn.commit().await?;
+        }
+        Ok(rowcount)
     }
 
-    fn calc_blr(&self) -> Vec<u8> {
-        let ln = self.xsqlda.len() * 2;
-        let mut blr: Vec<u8> = vec![5, 2, 4, 0, (ln & 255) as u8, (ln >> 8) as u8];
-
-        for x in &self.xsqlda {
-            let map = hashmap! {
-                SQL_TYPE_VARYING => vec![37, (x.sqllen & 255) as u8, (x.sqllen >> 8) as u8],
-                SQL_TYPE_TEXT => vec![14, (x.sqllen & 255) as u8, (x.sqllen >> 8) as u8],
-                SQL_TYPE_LONG => vec![8, x.sqlscale as u8],
-                SQL_TYPE_SHORT => vec![7, x.sqlscale as u8],
-                SQL_TYPE_INT64 => vec![16, x.sqlscale as u8],
-                SQL_TYPE_INT128 => vec![26, x.sqlscale as u8],
-                SQL_TYPE_QUAD => vec![9, x.sqlscale as u8],
-                SQL_TYPE_DEC_FIXED => vec![26, x.sqlscale as u8],
-                SQL_TYPE_DOUBLE => vec![27],
-                SQL_TYPE_FLOAT => vec![10],
-                SQL_TYPE_D_FLOAT => vec![11],
-                SQL_TYPE_DATE => vec![12],
-                SQL_TYPE_TIME => vec![13],
-                SQL_TYPE_TIMESTAMP => vec![35],
-                SQL_TYPE_BLOB => vec![9, 0],
-                SQL_TYPE_ARRAY => vec![9, 0],
-                SQL_TYPE_BOOLEAN => vec![23],
-                SQL_TYPE_DEC64 => vec![24],
-                SQL_TYPE_DEC128 => vec![25],
-                SQL_TYPE_TIME_TZ => vec![28],
-                SQL_TYPE_TIMESTAMP_TZ => vec![29],
-            };
-            blr.extend(&map[&x.sqltype]);
-            blr.extend(vec![7, 0]);
+    // EXECUTE PROCEDURE with output parameters, or INSERT/UPDATE/DELETE ... RETURNING,
+    // the values are returned as a row
+    pub async fn execute_procedure<P: Params>(&mut self, params: P) -> Result<Row, Error> {
+        if self.stmt_type != ISC_INFO_SQL_STMT_EXEC_PROCEDURE {
+            return Err(Error::ValueError(ValueError::new(&format!(
+                "statement is not EXECUTE PROCEDURE: statement type {}",
+                self.stmt_type
+            ))));
         }
-        blr.extend(vec![255, 76]);
+        self.bind(params).await?;
+        let row = self
+            .conn
+            ._execute_procedure(
+                self.trans_handle,
+                self.stmt_handle,
+                self.params.as_slice(),
+                &self.xsqlda,
+            )
+            .await?;
+        // UPDATE ... RETURNING which matched no row returns NULLs
+        let row = if row.is_empty() {
+            vec![CellValue::Null; self.xsqlda.len()]
+        } else {
+            row
+        };
+        let mut rows = VecDeque::from(vec![row]);
+        let error = match self.convert_rows(&mut rows, self.trans_handle).await {
+            Ok(error) => error,
+            Err(e) => Some(e),
+        };
+        if let Some(e) = error {
+            // the values can't be returned, so the write isn't committed either
+            if self.autocommit {
+                self.conn._rollback(self.trans_handle).await?;
+            }
+            return Err(e);
+        }
+        if self.autocommit {
+            self.conn.commit().await?;
+        }
+        Ok(Row::new(
+            rows.pop_front().unwrap(),
+            Arc::new(self.metadata()),
+        ))
+    }
 
-        blr
+    // the first row of a SELECT, or the values of INSERT ... RETURNING
+    pub async fn query_row<P: Params>(&mut self, params: P) -> Result<Row, Error> {
+        if self.stmt_type == ISC_INFO_SQL_STMT_EXEC_PROCEDURE {
+            return self.execute_procedure(params).await;
+        }
+        // checked before the execution, a DML without RETURNING would be committed
+        if self.stmt_type != ISC_INFO_SQL_STMT_SELECT {
+            return Err(Error::ValueError(ValueError::new(&format!(
+                "statement returns no rows: statement type {}",
+                self.stmt_type
+            ))));
+        }
+        let mut rows = self.query_lazy(params).await?;
+        match rows.next().await {
+            Some(row) => row,
+            None => Err(Error::ValueError(ValueError::new("query returned no rows"))),
+        }
+    }
+
+    // bind the fields of record in declaration order and execute
+    pub async fn execute_struct<T: ToParams>(&mut self, record: &T) -> Result<usize, Error> {
+        let params = record.to_params();
+        let params: Vec<&dyn ToSqlParam> = params.iter().map(|p| p as &dyn ToSqlParam).collect();
+        self.execute(params.as_slice()).await
+    }
+
+    // execute once per parameter set and return the total affected row count
+    pub async fn execute_all<I, P>(&mut self, iter: I) -> Result<usize, Error>
+    where
+        I: IntoIterator<Item = P>,
+        P: Params,
+    {
+        let mut rowcount = 0;
+        for params in iter {
+            self.bind(params).await?;
+            rowcount += self
+                .conn
+                ._execute_statement(
+                    self.trans_handle,
+                    self.stmt_handle,
+                    self.stmt_type,
+                    self.params.as_slice(),
+                )
+                .await?;
+            if self.stmt_type == ISC_INFO_SQL_STMT_SELECT {
+                self.cursor_open = true;
+                self.close_cursor().await;
+            }
+        }
+        if self.autocommit {
+            self.conn.commit().await?;
+        }
+        Ok(rowcount)
     }
 }
 
 impl Drop for StatementAsync<'_> {
     fn drop(&mut self) {
-        task::block_on(self.conn._free_statement(self.stmt_handle, DSQL_DROP));
+        // see Drop for WireProtocolAsync
+        if !self.closed {
+            self.conn.defer_free_statement(self.stmt_handle, DSQL_DROP);
+        }
+    }
+}
+
+pub struct LazyRowsAsync<'stmt, 'conn> {
+    stmt: &'stmt mut StatementAsync<'conn>,
+    blr: Vec<u8>,
+    rows: VecDeque<Vec<CellValue>>,
+    more_data: bool,
+    error: Option<Error>, // stopped decoding the last batch, returned after its rows
+    metadata: Arc<ResultSetMetaData>,
+}
+
+impl LazyRowsAsync<'_, '_> {
+    pub fn metadata(&self) -> &ResultSetMetaData {
+        &self.metadata
+    }
+
+    async fn fetch(&mut self) -> Result<(), Error> {
+        let stmt = &mut *self.stmt;
+        let (rows, status, error) = stmt
+            .conn
+            ._fetch(stmt.stmt_handle, &self.blr, &stmt.xsqlda)
+            .await?;
+        let mut rows = VecDeque::from(rows);
+        let error = stmt
+            .convert_rows(&mut rows, stmt.trans_handle)
+            .await?
+            .or(error);
+        self.rows = rows;
+        self.more_data = status == FETCH_OK && error.is_none();
+        self.error = error;
+        Ok(())
+    }
+
+    pub async fn next(&mut self) -> Option<Result<Row, Error>> {
+        if self.rows.is_empty() && self.more_data {
+            if let Err(e) = self.fetch().await {
+                self.more_data = false;
+                self.stmt.close_cursor().await;
+                return Some(Err(e));
+            }
+        }
+        match self.rows.pop_front() {
+            Some(row) => Some(Ok(Row::new(row, self.metadata.clone()))),
+            None => {
+                self.stmt.close_cursor().await;
+                self.error.take().map(Err)
+            }
+        }
+    }
+
+    // release the server side cursor before all the rows are read
+    pub async fn close(self) {
+        self.stmt.close_cursor().await;
+    }
+
+    // like Rows::write_csv(), the rows are written as they are fetched
+    pub async fn write_csv<W: Write>(self, w: &mut W, header: bool) -> Result<(), Error> {
+        self.write_delimited(w, ',', header).await
+    }
+
+    pub async fn write_tsv<W: Write>(self, w: &mut W, header: bool) -> Result<(), Error> {
+        self.write_delimited(w, '\t', header).await
+    }
+
+    async fn write_delimited<W: Write>(
+        mut self,
+        w: &mut W,
+        delimiter: char,
+        header: bool,
+    ) -> Result<(), Error> {
+        if header {
+            if let Err(e) = row::write_delimited_header(w, &self.metadata, delimiter) {
+                self.close().await;
+                return Err(e);
+            }
+        }
+        while let Some(row) = self.next().await {
+            if let Err(e) = row::write_delimited_row(w, &row?, delimiter) {
+                self.close().await;
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for LazyRowsAsync<'_, '_> {
+    fn drop(&mut self) {
+        // see Drop for WireProtocolAsync, the cursor is closed at the next execution
+        if self.stmt.cursor_open {
+            debug_print!("LazyRowsAsync dropped without close()");
+        }
     }
 }
@@ -19,14 +19,34 @@
 // LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
-use super::ConnectionAsync;
+use super::{BlobId, ConnectionAsync, PoolAsync};
+use super::{PTYPE_BATCH_SEND, PTYPE_LAZY_SEND};
 use async_std::task;
 use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
 use rust_decimal::Decimal;
 use rust_decimal_macros::dec;
 use std::env;
+use std::time::Duration;
 use urlencoding;
 
+// url of the test database /tmp/{db_name}, which may be followed by ?options
+fn test_conn_string(db_name: &str) -> String {
+    let user = match env::var("ISC_USER") {
+        Ok(val) => val,
+        Err(_) => "sysdba".to_string(),
+    };
+    let password = match env::var("ISC_PASSWORD") {
+        Ok(val) => val,
+        Err(_) => "masterkey".to_string(),
+    };
+    format!(
+        "firebird://{}:{}@localhost/tmp/{}",
+        &user,
+        urlencoding::encode(&password),
+        db_name
+    )
+}
+
 #[derive(PartialEq, Debug)]
 struct Foo {
     a: i32,
@@ -266,10 +286,13 @@ async fn test_connnect_async() {
     }];
 
     let mut trans = conn.transaction().await.unwrap();
-    trans
-        .execute("delete from foo where a in (1, 3)", ())
-        .await
-        .unwrap();
+    assert_eq!(
+        trans
+            .execute("delete from foo where a in (1, 3)", ())
+            .await
+            .unwrap(),
+        2
+    );
 
     let mut stmt = trans.prepare("select * from foo").await.unwrap();
     let foo_iter = stmt
@@ -298,3 +321,323 @@ async fn test_connnect_async() {
 fn test_connnect() {
     task::block_on(test_connnect_async())
 }
+
+// rows of a MON$ table for this attachment, in a new transaction for a fresh snapshot
+async fn monitoring_count(conn: &mut ConnectionAsync, table: &str) -> i64 {
+    let mut trans = conn.transaction().await.unwrap();
+    let mut stmt = trans
+        .prepare(&format!(
+            "select count(*) from {} where mon$attachment_id = current_connection",
+            table
+        ))
+        .await
+        .unwrap();
+    let count: i64 = stmt
+        .query(())
+        .await
+        .unwrap()
+        .next()
+        .unwrap()
+        .get(0)
+        .unwrap();
+    stmt.close().await;
+    trans.close().await.unwrap();
+    count
+}
+
+async fn test_statement_close_async() {
+    let conn_string = test_conn_string("rust-firebird-test-statement-close-async.fdb");
+
+    let mut conn = ConnectionAsync::create_database(&conn_string)
+        .await
+        .unwrap();
+    let count = monitoring_count(&mut conn, "mon$statements").await;
+
+    let stmt = conn.prepare("select 1 from rdb$database").await.unwrap();
+    stmt.close().await;
+    // dropped inside the task, freed with the next request
+    for _ in 0..3 {
+        let mut stmt = conn.prepare("select 1 from rdb$database").await.unwrap();
+        assert_eq!(stmt.query(()).await.unwrap().count(), 1);
+        drop(stmt);
+    }
+    assert_eq!(monitoring_count(&mut conn, "mon$statements").await, count);
+}
+
+#[test]
+fn test_statement_close() {
+    task::block_on(test_statement_close_async())
+}
+
+async fn test_transaction_close_async() {
+    let conn_string = test_conn_string("rust-firebird-test-transaction-close-async.fdb");
+
+    let mut conn = ConnectionAsync::create_database(&conn_string)
+        .await
+        .unwrap();
+    conn.execute_batch("CREATE TABLE foo (a INTEGER NOT NULL)")
+        .await
+        .unwrap();
+    let count = monitoring_count(&mut conn, "mon$transactions").await;
+
+    let mut trans = conn.transaction().await.unwrap();
+    trans
+        .execute("insert into foo(a) values (1)", ())
+        .await
+        .unwrap();
+    trans.close().await.unwrap();
+    // dropped inside the task, rolled back with the next request
+    let mut trans = conn.transaction().await.unwrap();
+    trans
+        .execute("insert into foo(a) values (2)", ())
+        .await
+        .unwrap();
+    drop(trans);
+    assert_eq!(monitoring_count(&mut conn, "mon$transactions").await, count);
+
+    let mut stmt = conn.prepare("select count(*) from foo").await.unwrap();
+    let rows: i64 = stmt
+        .query(())
+        .await
+        .unwrap()
+        .next()
+        .unwrap()
+        .get(0)
+        .unwrap();
+    assert_eq!(rows, 0);
+}
+
+#[test]
+fn test_transaction_close() {
+    task::block_on(test_transaction_close_async())
+}
+
+async fn test_pool_async() {
+    let conn_string = test_conn_string("rust-firebird-test-pool-async.fdb");
+
+    {
+        let mut conn = ConnectionAsync::create_database(&conn_string)
+            .await
+            .unwrap();
+        conn.execute_batch("CREATE TABLE foo (a INTEGER NOT NULL)")
+            .await
+            .unwrap();
+    }
+
+    let pool = PoolAsync::new(&conn_string, 1);
+    {
+        let mut conn1 = pool.get().await.unwrap();
+        let mut conn2 = pool.get().await.unwrap();
+        assert!(conn1.is_valid().await);
+        assert!(conn2.is_valid().await);
+        conn1.release().await;
+        conn2.release().await;
+    }
+    // only max_idle connections are kept
+    assert_eq!(pool.idle_count(), 1);
+
+    let mut conn = pool.get().await.unwrap();
+    assert_eq!(pool.idle_count(), 0);
+    conn.execute("insert into foo(a) values (1)", ())
+        .await
+        .unwrap();
+    conn.release().await;
+    assert_eq!(pool.idle_count(), 1);
+
+    // a dropped connection isn't returned
+    let conn = pool.get().await.unwrap();
+    drop(conn);
+    assert_eq!(pool.idle_count(), 0);
+
+    let conn = ConnectionAsync::connect(&conn_string).await.unwrap();
+    conn.close().await.unwrap();
+}
+
+#[test]
+fn test_pool() {
+    task::block_on(test_pool_async())
+}
+
+async fn test_prepare_describe_async() {
+    let conn_string = test_conn_string("rust-firebird-test-describe-async.fdb");
+
+    // too many columns for one describe buffer
+    let names: Vec<String> = (1..=100).map(|i| format!("COLUMN_{}", i)).collect();
+    let columns: Vec<String> = names.iter().map(|n| format!("{} INTEGER", n)).collect();
+    let mut conn = ConnectionAsync::create_database(&conn_string)
+        .await
+        .unwrap();
+    conn.execute_batch(&format!("CREATE TABLE wide ({})", columns.join(", ")))
+        .await
+        .unwrap();
+
+    let mut stmt = conn.prepare("select * from wide").await.unwrap();
+    assert_eq!(stmt.column_count(), 100);
+    assert_eq!(stmt.column_names(), names);
+    assert_eq!(stmt.query(()).await.unwrap().count(), 0);
+}
+
+#[test]
+fn test_prepare_describe() {
+    task::block_on(test_prepare_describe_async())
+}
+
+async fn test_prepare_lazy_and_non_lazy_async() {
+    let conn_string = test_conn_string("rust-firebird-test-prepare-async.fdb");
+
+    let mut conn = ConnectionAsync::create_database(&conn_string)
+        .await
+        .unwrap();
+    conn.execute_batch("CREATE TABLE foo (a INTEGER NOT NULL, b VARCHAR(30), h BLOB SUB_TYPE 1)")
+        .await
+        .unwrap();
+
+    // same statements whether the server deferred the responses or not
+    for accept_type in [PTYPE_LAZY_SEND, PTYPE_BATCH_SEND] {
+        conn.wp.borrow_mut().accept_type = accept_type;
+        assert_eq!(conn.uses_lazy_send(), accept_type == PTYPE_LAZY_SEND);
+
+        conn.execute("delete from foo", ()).await.unwrap();
+        conn.execute(
+            "insert into foo(a, b, h) values (?, ?, ?)",
+            (1, "a", "blob"),
+        )
+        .await
+        .unwrap();
+        let mut stmt = conn.prepare("select a, b, h from foo").await.unwrap();
+        let row = stmt.query(()).await.unwrap().next().unwrap();
+        let a: i32 = row.get(0).unwrap();
+        let b: String = row.get(1).unwrap();
+        let h: String = row.get(2).unwrap();
+        assert_eq!((a, b.as_str(), h.as_str()), (1, "a", "blob"));
+        assert_eq!(stmt.query(()).await.unwrap().count(), 1);
+    }
+}
+
+#[test]
+fn test_prepare_lazy_and_non_lazy() {
+    task::block_on(test_prepare_lazy_and_non_lazy_async())
+}
+
+async fn test_blob_reader_async() {
+    let conn_string = test_conn_string("rust-firebird-test-blob-reader-async.fdb");
+
+    let mut conn = ConnectionAsync::create_database(&conn_string)
+        .await
+        .unwrap();
+    conn.execute_batch("CREATE TABLE docs (data BLOB SUB_TYPE 0)")
+        .await
+        .unwrap();
+    // more than one segment
+    let data: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8).collect();
+    conn.execute("insert into docs(data) values (?)", (data.as_slice(),))
+        .await
+        .unwrap();
+
+    let mut stmt = conn.prepare("select data from docs").await.unwrap();
+    stmt.set_fetch_blobs(false);
+    let blob_id: BlobId = stmt
+        .query(())
+        .await
+        .unwrap()
+        .next()
+        .unwrap()
+        .get(0)
+        .unwrap();
+
+    let mut reader = stmt.blob_reader(&blob_id).await.unwrap();
+    let mut read = Vec::new();
+    let mut chunk = [0u8; 7];
+    loop {
+        let n = reader.read(&mut chunk).await.unwrap();
+        if n == 0 {
+            break;
+        }
+        read.extend_from_slice(&chunk[..n]);
+    }
+    reader.close().await.unwrap();
+    assert_eq!(read, data);
+}
+
+#[test]
+fn test_blob_reader() {
+    task::block_on(test_blob_reader_async())
+}
+
+async fn test_query_lazy_async() {
+    let conn_string = test_conn_string("rust-firebird-test-query-lazy-async.fdb");
+
+    let mut conn = ConnectionAsync::create_database(&conn_string)
+        .await
+        .unwrap();
+    conn.execute_batch("CREATE TABLE foo (a INTEGER NOT NULL)")
+        .await
+        .unwrap();
+    let mut stmt = conn.prepare("insert into foo(a) values (?)").await.unwrap();
+    stmt.execute_all((1..=1000).map(|i| (i,))).await.unwrap();
+    drop(stmt);
+
+    let mut stmt = conn.prepare("select a from foo order by a").await.unwrap();
+    let mut rows = stmt.query_lazy(()).await.unwrap();
+    let mut sum = 0;
+    while let Some(row) = rows.next().await {
+        sum += row.unwrap().get::<i32>(0).unwrap();
+    }
+    assert_eq!(sum, 500500);
+    drop(rows);
+
+    // closed early
+    let mut rows = stmt.query_lazy(()).await.unwrap();
+    assert_eq!(
+        rows.next().await.unwrap().unwrap().get::<i32>(0).unwrap(),
+        1
+    );
+    rows.close().await;
+    let mut rows = stmt.query_lazy(()).await.unwrap();
+    assert_eq!(
+        rows.next().await.unwrap().unwrap().get::<i32>(0).unwrap(),
+        1
+    );
+    // dropped early, the cursor is closed at the next execution
+    drop(rows);
+    let mut rows = stmt.query_lazy(()).await.unwrap();
+    assert_eq!(
+        rows.next().await.unwrap().unwrap().get::<i32>(0).unwrap(),
+        1
+    );
+}
+
+#[test]
+fn test_query_lazy() {
+    task::block_on(test_query_lazy_async())
+}
+
+async fn test_event_conduit_async() {
+    let conn_string = test_conn_string("rust-firebird-test-event-conduit-async.fdb");
+
+    let mut conn = ConnectionAsync::create_database(&conn_string)
+        .await
+        .unwrap();
+    let mut other = ConnectionAsync::connect(&conn_string).await.unwrap();
+
+    let mut conduit = conn.event_conduit(&["ev_a", "ev_b"]).await.unwrap();
+    other
+        .execute_batch("EXECUTE BLOCK AS BEGIN POST_EVENT 'ev_a'; END")
+        .await
+        .unwrap();
+    let counts = conduit.wait().await.unwrap();
+    assert_eq!(counts["ev_a"], 1);
+    assert_eq!(counts["ev_b"], 0);
+
+    assert!(conduit
+        .wait_timeout(Duration::from_millis(100))
+        .await
+        .unwrap()
+        .is_none());
+    conduit.cancel().await.unwrap();
+}
+
+#[test]
+fn test_event_conduit() {
+    task::block_on(test_event_conduit_async())
+}
@@ -19,13 +19,40 @@
 // LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
-use super::Connection;
+use super::{
+    blob_file, sql_rows, BlobId, CellValue, Charset, Connection, Error, ExecutionMode, ObjectKind,
+    Param,
+};
+use super::{PTYPE_BATCH_SEND, PTYPE_LAZY_SEND};
 use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
 use rust_decimal::Decimal;
 use rust_decimal_macros::dec;
 use std::env;
+use std::io::Read;
+use std::time::Duration;
 use urlencoding;
 
+fn test_user() -> String {
+    match env::var("ISC_USER") {
+        Ok(val) => val,
+        Err(_) => "sysdba".to_string(),
+    }
+}
+
+// url of the test database /tmp/{db_name}, which may be followed by ?options
+fn test_conn_string(db_name: &str) -> String {
+    let password = match env::var("ISC_PASSWORD") {
+        Ok(val) => val,
+        Err(_) => "masterkey".to_string(),
+    };
+    format!(
+        "firebird://{}:{}@localhost/tmp/{}",
+        test_user(),
+        urlencoding::encode(&password),
+        db_name
+    )
+}
+
 #[derive(PartialEq, Debug)]
 struct Foo {
     a: i32,
@@ -67,6 +94,9 @@ fn test_connnect() {
             panic!("Can't connect");
         }
     }
+    conn.ping().unwrap();
+    assert!(conn.is_valid());
+    assert!(conn.take_warnings().is_empty());
 
     conn.execute_batch(
         r#"
@@ -287,3 +317,1320 @@ fn test_connnect() {
         assert_eq!(foo.unwrap(), expects[i]);
     }
 }
+
+#[test]
+fn test_read_only() {
+    let conn_string = test_conn_string("rust-firebird-test-read-only.fdb");
+
+    {
+        let mut conn = Connection::create_database(&conn_string).unwrap();
+        conn.execute_batch("CREATE TABLE foo (a INTEGER NOT NULL)")
+            .unwrap();
+        assert!(!conn.database_info().unwrap().read_only);
+    }
+
+    {
+        let mut conn = Connection::connect(&format!("{}?read_only=true", conn_string)).unwrap();
+        assert!(conn.execute("insert into foo(a) values (1)", ()).is_err());
+        let mut stmt = conn.prepare("select count(*) from foo").unwrap();
+        assert_eq!(stmt.query(()).unwrap().count(), 1);
+        // only the transactions are read only, not the database file
+        drop(stmt);
+        assert!(!conn.database_info().unwrap().read_only);
+    }
+
+    let mut conn = Connection::connect(&conn_string).unwrap();
+    conn.execute("insert into foo(a) values (1)", ()).unwrap();
+    let info = conn.database_info().unwrap();
+    assert!(!info.read_only);
+    assert!(info.ods_version >= 11);
+    assert_eq!(info.att_charset, 4); // UTF8
+}
+
+#[test]
+fn test_info_buffer_len() {
+    let conn_string =
+        test_conn_string("rust-firebird-test-info-buffer-len.fdb?info_buffer_len=4096");
+
+    let mut conn = Connection::create_database(&conn_string).unwrap();
+    // the describe of these columns doesn't fit in a small buffer
+    let columns: Vec<String> = (0..50)
+        .map(|i| format!("{} AS A_RATHER_LONG_COLUMN_NAME_{:02}", i, i))
+        .collect();
+    let query = format!("SELECT {} FROM RDB$DATABASE", columns.join(", "));
+    for len in [256, 65535] {
+        conn.set_info_buffer_len(len).unwrap();
+        let (metadata, rows) = conn.query_all(&query, ()).unwrap();
+        assert_eq!(metadata.column_count(), 50);
+        assert_eq!(
+            metadata.column_name(49),
+            Some("A_RATHER_LONG_COLUMN_NAME_49")
+        );
+        let last: i32 = rows[0].get(49).unwrap();
+        assert_eq!(last, 49);
+        assert!(conn.database_info().is_ok());
+    }
+    assert!(conn.set_info_buffer_len(0).is_err());
+    assert!(conn.set_info_buffer_len(1024 * 1024 + 1).is_err());
+}
+
+#[test]
+fn test_wide_view() {
+    let conn_string = test_conn_string("rust-firebird-test-wide-view.fdb");
+
+    let mut conn = Connection::create_database(&conn_string).unwrap();
+    // described over several continuations, past the 255th and 256th columns
+    let columns: Vec<String> = (1..=500).map(|i| format!("{} AS C{}", i, i)).collect();
+    conn.execute_batch(&format!(
+        "CREATE VIEW wide AS SELECT {} FROM RDB$DATABASE",
+        columns.join(", ")
+    ))
+    .unwrap();
+    conn.set_info_buffer_len(256).unwrap();
+    let (metadata, rows) = conn.query_all("SELECT * FROM wide", ()).unwrap();
+    assert_eq!(metadata.column_count(), 500);
+    for i in [0, 254, 255, 256, 499] {
+        assert_eq!(
+            metadata.column_name(i),
+            Some(format!("C{}", i + 1).as_str())
+        );
+        let v: i32 = rows[0].get(i).unwrap();
+        assert_eq!(v, i as i32 + 1);
+    }
+}
+
+#[test]
+fn test_object_exists() {
+    let conn_string = test_conn_string("rust-firebird-test-object-exists.fdb");
+
+    let mut conn = Connection::create_database(&conn_string).unwrap();
+    conn.execute_script(
+        r#"
+        CREATE TABLE foo (a INTEGER);
+        CREATE TABLE "Bar" (a INTEGER);
+        COMMIT;
+        CREATE INDEX foo_a ON foo (a);
+        CREATE SEQUENCE foo_seq;
+        CREATE TRIGGER foo_bi FOR foo BEFORE INSERT AS BEGIN END
+    "#,
+    )
+    .unwrap();
+
+    assert!(conn.object_exists(ObjectKind::Table, "foo").unwrap());
+    assert!(conn.object_exists(ObjectKind::Table, "FOO").unwrap());
+    assert!(conn.object_exists(ObjectKind::Table, "\"FOO\"").unwrap());
+    assert!(!conn.object_exists(ObjectKind::Table, "\"foo\"").unwrap());
+    assert!(conn.object_exists(ObjectKind::Table, "\"Bar\"").unwrap());
+    assert!(conn.object_exists(ObjectKind::Table, "bar").unwrap());
+    assert!(!conn.object_exists(ObjectKind::Table, "baz").unwrap());
+    assert!(conn.object_exists(ObjectKind::Index, "foo_a").unwrap());
+    assert!(conn
+        .object_exists(ObjectKind::Generator, "foo_seq")
+        .unwrap());
+    assert!(conn.object_exists(ObjectKind::Trigger, "foo_bi").unwrap());
+    assert!(!conn.object_exists(ObjectKind::Trigger, "foo_a").unwrap());
+}
+
+#[test]
+fn test_query_count_and_page() {
+    let conn_string = test_conn_string("rust-firebird-test-count-and-page.fdb");
+
+    let mut conn = Connection::create_database(&conn_string).unwrap();
+    conn.execute_batch("CREATE TABLE foo (a INTEGER NOT NULL)")
+        .unwrap();
+    {
+        let mut stmt = conn.prepare("insert into foo(a) values (?)").unwrap();
+        stmt.execute_all((1..=30).map(|i| (i,))).unwrap();
+    }
+
+    let query = "select a from foo where a > ? order by a desc";
+    let (total, rows) = conn.query_count_and_page(query, (5,), 20, 10).unwrap();
+    assert_eq!(total, 25);
+    let values: Vec<i32> = rows.iter().map(|row| row.get(0).unwrap()).collect();
+    assert_eq!(values, vec![10, 9, 8, 7, 6]);
+
+    let (total, rows) = conn.query_count_and_page(query, (5,), 0, 0).unwrap();
+    assert_eq!((total, rows.len()), (25, 0));
+}
+
+#[test]
+fn test_statement_execute() {
+    let conn_string = test_conn_string("rust-firebird-test-statement-execute.fdb");
+
+    let mut conn = Connection::create_database(&conn_string).unwrap();
+    conn.execute_batch("CREATE TABLE foo (a INTEGER NOT NULL)")
+        .unwrap();
+
+    let mut stmt = conn.prepare("insert into foo(a) values (?)").unwrap();
+    for i in 1..=5 {
+        assert_eq!(stmt.execute((i,)).unwrap(), 1);
+    }
+    drop(stmt);
+    let mut stmt = conn
+        .prepare("update foo set a = a + 10 where a > ?")
+        .unwrap();
+    assert_eq!(stmt.execute((2,)).unwrap(), 3);
+    assert_eq!(stmt.execute((100,)).unwrap(), 0);
+    drop(stmt);
+    let mut stmt = conn.prepare("select a from foo").unwrap();
+    assert_eq!(stmt.execute(()).unwrap(), 5);
+    drop(stmt);
+
+    assert_eq!(
+        conn.execute("update foo set a = a + 1 where a > ?", (10,))
+            .unwrap(),
+        3
+    );
+    let mut trans = conn.transaction().unwrap();
+    assert_eq!(trans.execute("delete from foo", ()).unwrap(), 5);
+    trans.rollback().unwrap();
+}
+
+#[test]
+fn test_context() {
+    let conn_string = test_conn_string("rust-firebird-test-context.fdb");
+
+    let mut conn = Connection::create_database(&conn_string).unwrap();
+    assert_eq!(conn.get_context("USER_SESSION", "TENANT").unwrap(), None);
+    conn.set_context("USER_SESSION", "TENANT", Some("acme"))
+        .unwrap();
+    assert_eq!(
+        conn.get_context("USER_SESSION", "TENANT").unwrap(),
+        Some("acme".to_string())
+    );
+    conn.set_context("USER_SESSION", "TENANT", None).unwrap();
+    assert_eq!(conn.get_context("USER_SESSION", "TENANT").unwrap(), None);
+
+    assert!(conn
+        .get_context("SYSTEM", "ENGINE_VERSION")
+        .unwrap()
+        .is_some());
+    assert!(conn
+        .set_context("SYSTEM", "ENGINE_VERSION", Some("0"))
+        .is_err());
+}
+
+#[test]
+fn test_execute_struct() {
+    let conn_string = test_conn_string("rust-firebird-test-execute-struct.fdb");
+
+    crate::to_params! {
+        struct Record {
+            a: i32,
+            b: String,
+            c: Option<String>,
+        }
+    }
+
+    let mut conn = Connection::create_database(&conn_string).unwrap();
+    conn.execute_batch("CREATE TABLE foo (a INTEGER NOT NULL, b VARCHAR(10), c VARCHAR(10))")
+        .unwrap();
+
+    let mut stmt = conn
+        .prepare("insert into foo(a, b, c) values (?, ?, ?)")
+        .unwrap();
+    let records = [
+        Record {
+            a: 1,
+            b: "one".to_string(),
+            c: Some("x".to_string()),
+        },
+        Record {
+            a: 2,
+            b: "two".to_string(),
+            c: None,
+        },
+    ];
+    for r in &records {
+        assert_eq!(stmt.execute_struct(r).unwrap(), 1);
+    }
+    drop(stmt);
+
+    let mut stmt = conn.prepare("select a, b, c from foo order by a").unwrap();
+    let rows: Vec<(i32, String, Option<String>)> = stmt
+        .query_map((), |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+        .unwrap()
+        .map(|r| r.unwrap())
+        .collect();
+    assert_eq!(
+        rows,
+        vec![
+            (1, "one".to_string(), Some("x".to_string())),
+            (2, "two".to_string(), None)
+        ]
+    );
+}
+
+#[test]
+fn test_blob_string_charset() {
+    let conn_string = test_conn_string("rust-firebird-test-blob-string-charset.fdb");
+
+    let mut conn = Connection::create_database(&conn_string).unwrap();
+    conn.execute_batch("CREATE TABLE foo (b BLOB SUB_TYPE TEXT CHARACTER SET WIN1252)")
+        .unwrap();
+    // blob contents are stored and read back as is, in the column's charset
+    let win1252: &[u8] = b"caf\xe9 \x80";
+    conn.execute("insert into foo(b) values (?)", (win1252,))
+        .unwrap();
+
+    let mut stmt = conn.prepare("select b from foo").unwrap();
+    let row = stmt.query(()).unwrap().next().unwrap();
+    assert_eq!(row.get_blob_string(0, Charset::Win1252).unwrap(), "café €");
+}
+
+#[test]
+fn test_query_lazy() {
+    let conn_string = test_conn_string("rust-firebird-test-query-lazy.fdb");
+
+    let mut conn = Connection::create_database(&conn_string).unwrap();
+    conn.execute_batch("CREATE TABLE foo (a INTEGER NOT NULL)")
+        .unwrap();
+    let mut stmt = conn.prepare("insert into foo(a) values (?)").unwrap();
+    stmt.execute_all((1..=1000).map(|i| (i,))).unwrap();
+    drop(stmt);
+
+    // more rows than one fetch batch
+    let mut stmt = conn.prepare("select a from foo order by a").unwrap();
+    let values: Vec<i32> = stmt
+        .query_lazy(())
+        .unwrap()
+        .map(|row| row.unwrap().get(0).unwrap())
+        .collect();
+    assert_eq!(values, (1..=1000).collect::<Vec<i32>>());
+
+    // written as fetched
+    let mut buf: Vec<u8> = Vec::new();
+    stmt.query_lazy(())
+        .unwrap()
+        .write_csv(&mut buf, true)
+        .unwrap();
+    let csv = String::from_utf8(buf).unwrap();
+    assert_eq!(csv.lines().count(), 1001);
+    assert!(csv.starts_with("A\r\n1\r\n2\r\n"));
+
+    // dropped early, the cursor is closed for the next execution
+    let first: Vec<i32> = stmt
+        .query_lazy(())
+        .unwrap()
+        .take(3)
+        .map(|row| row.unwrap().get(0).unwrap())
+        .collect();
+    assert_eq!(first, vec![1, 2, 3]);
+    let mut rows = stmt.query_lazy(()).unwrap();
+    assert_eq!(rows.next().unwrap().unwrap().get::<i32>(0).unwrap(), 1);
+    drop(rows);
+    drop(stmt);
+
+    // not a select, nothing to iterate
+    let mut stmt = conn.prepare("delete from foo where a > 10").unwrap();
+    assert!(stmt.query_lazy(()).unwrap().next().is_none());
+    drop(stmt);
+    let mut stmt = conn.prepare("select count(*) from foo").unwrap();
+    let count: i64 = stmt.query(()).unwrap().next().unwrap().get(0).unwrap();
+    assert_eq!(count, 10);
+}
+
+#[test]
+fn test_no_transaction() {
+    let conn_string = test_conn_string("rust-firebird-test-no-transaction.fdb");
+
+    let mut conn = Connection::create_database(&conn_string).unwrap();
+    conn.execute_batch("CREATE TABLE foo (a INTEGER NOT NULL)")
+        .unwrap();
+    conn.execute_batch("COMMIT").unwrap();
+
+    // detected before anything is sent to the server
+    assert!(matches!(
+        conn.execute("insert into foo(a) values (1)", ()),
+        Err(Error::NoTransaction)
+    ));
+    assert!(matches!(conn.commit(), Err(Error::NoTransaction)));
+    assert!(matches!(
+        conn.prepare("select a from foo"),
+        Err(Error::NoTransaction)
+    ));
+
+    conn.reset_transaction().unwrap();
+    assert_eq!(
+        conn.execute("insert into foo(a) values (1)", ()).unwrap(),
+        1
+    );
+
+    // retained in a script
+    conn.execute_script("insert into foo(a) values (2); ROLLBACK; insert into foo(a) values (3);")
+        .unwrap();
+    let mut stmt = conn.prepare("select count(*) from foo").unwrap();
+    let count: i64 = stmt.query(()).unwrap().next().unwrap().get(0).unwrap();
+    assert_eq!(count, 2);
+}
+
+#[test]
+fn test_limbo_transactions() {
+    let conn_string = test_conn_string("rust-firebird-test-limbo-transactions.fdb");
+
+    let mut conn = Connection::create_database(&conn_string).unwrap();
+    assert_eq!(
+        conn.recover_limbo_transactions().unwrap(),
+        Vec::<u64>::new()
+    );
+    // not in limbo
+    let id = conn.transaction_id().unwrap();
+    assert!(conn.commit_limbo(id).is_err());
+    assert!(conn.rollback_limbo(u32::MAX as u64).is_err());
+}
+
+#[test]
+fn test_execute_procedure() {
+    let conn_string = test_conn_string("rust-firebird-test-execute-procedure.fdb");
+
+    let mut conn = Connection::create_database(&conn_string).unwrap();
+    conn.execute_batch(
+        "CREATE PROCEDURE add_mul (a INTEGER, b INTEGER)
+            RETURNS (s INTEGER, p INTEGER, t VARCHAR(10))
+        AS
+        BEGIN
+            s = a + b;
+            p = a * b;
+            t = NULL;
+        END",
+    )
+    .unwrap();
+
+    let mut stmt = conn.prepare("execute procedure add_mul(?, ?)").unwrap();
+    let row = stmt.execute_procedure((3, 4)).unwrap();
+    assert_eq!(row.get::<i32>(0).unwrap(), 7);
+    assert_eq!(row.get::<i32>(1).unwrap(), 12);
+    assert_eq!(row.get::<Option<String>>(2).unwrap(), None);
+    let row = stmt.execute_procedure((5, 6)).unwrap();
+    assert_eq!(row.get::<i32>(0).unwrap(), 11);
+    drop(stmt);
+
+    let mut stmt = conn.prepare("select 1 from rdb$database").unwrap();
+    assert!(stmt.execute_procedure(()).is_err());
+}
+
+#[test]
+fn test_query_row() {
+    let conn_string = test_conn_string("rust-firebird-test-query-row.fdb");
+
+    let mut conn = Connection::create_database(&conn_string).unwrap();
+    conn.execute_batch(
+        "CREATE TABLE foo (
+            a INTEGER GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+            b VARCHAR(10),
+            c VARCHAR(10)
+        )",
+    )
+    .unwrap();
+
+    let id: i32 = conn
+        .prepare("insert into foo(b) values (?) returning a")
+        .unwrap()
+        .query_row(("x",))
+        .unwrap()
+        .get(0)
+        .unwrap();
+    assert_eq!(id, 1);
+
+    // NULL column in RETURNING
+    let row = conn
+        .prepare("insert into foo(b) values (?) returning a, c")
+        .unwrap()
+        .query_row(("y",))
+        .unwrap();
+    assert_eq!(row.get::<i32>(0).unwrap(), 2);
+    assert_eq!(row.get::<Option<String>>(1).unwrap(), None);
+
+    // UPDATE ... RETURNING which matches no row
+    let row = conn
+        .prepare("update foo set c = ? where a = ? returning a, b")
+        .unwrap()
+        .query_row(("z", 100))
+        .unwrap();
+    assert_eq!(row.get::<Option<i32>>(0).unwrap(), None);
+    assert_eq!(row.get::<Option<String>>(1).unwrap(), None);
+
+    let mut stmt = conn.prepare("select b from foo where a = ?").unwrap();
+    assert_eq!(stmt.query_row((2,)).unwrap().get::<String>(0).unwrap(), "y");
+    assert!(stmt.query_row((100,)).is_err());
+    drop(stmt);
+
+    // rejected before the execution, nothing is written
+    assert!(conn
+        .prepare("insert into foo(b) values (?)")
+        .unwrap()
+        .query_row(("w",))
+        .is_err());
+    let mut stmt = conn.prepare("select count(*) from foo").unwrap();
+    assert_eq!(stmt.query_row(()).unwrap().get::<i32>(0).unwrap(), 2);
+}
+
+#[test]
+fn test_max_rows() {
+    let conn_string = test_conn_string("rust-firebird-test-max-rows.fdb");
+
+    let mut conn = Connection::create_database(&conn_string).unwrap();
+    conn.execute_batch("CREATE TABLE foo (a INTEGER NOT NULL)")
+        .unwrap();
+    let mut stmt = conn.prepare("insert into foo(a) values (?)").unwrap();
+    stmt.execute_all((1..=1000).map(|i| (i,))).unwrap();
+    drop(stmt);
+
+    let mut stmt = conn.prepare("select a from foo order by a").unwrap();
+    stmt.set_max_rows(10);
+    let values: Vec<i32> = stmt.query(()).unwrap().map(|r| r.get(0).unwrap()).collect();
+    assert_eq!(values, (1..=10).collect::<Vec<i32>>());
+    assert!(stmt.rows_truncated());
+
+    // the cursor is closed, the statement can be executed again
+    stmt.set_max_rows(2000);
+    assert_eq!(stmt.query(()).unwrap().count(), 1000);
+    assert!(!stmt.rows_truncated());
+}
+
+#[test]
+fn test_event_conduit() {
+    let conn_string = test_conn_string("rust-firebird-test-event-conduit.fdb");
+
+    let mut conn = Connection::create_database(&conn_string).unwrap();
+    let mut other = Connection::connect(&conn_string).unwrap();
+    assert!(conn.event_conduit(&[]).is_err());
+
+    let mut conduit = conn.event_conduit(&["ev_a", "ev_b"]).unwrap();
+    other
+        .execute_batch(
+            "EXECUTE BLOCK AS
+            BEGIN
+                POST_EVENT 'ev_a';
+                POST_EVENT 'ev_b';
+            END",
+        )
+        .unwrap();
+    let counts = conduit.wait().unwrap();
+    assert_eq!(counts["ev_a"], 1);
+    assert_eq!(counts["ev_b"], 1);
+
+    other
+        .execute_batch("EXECUTE BLOCK AS BEGIN POST_EVENT 'ev_b'; END")
+        .unwrap();
+    let counts = conduit.wait().unwrap();
+    assert_eq!(counts["ev_a"], 0);
+    assert_eq!(counts["ev_b"], 1);
+
+    // nothing posted, the events stay queued
+    assert!(conduit
+        .wait_timeout(Duration::from_millis(100))
+        .unwrap()
+        .is_none());
+    other
+        .execute_batch("EXECUTE BLOCK AS BEGIN POST_EVENT 'ev_a'; END")
+        .unwrap();
+    let counts = conduit
+        .wait_timeout(Duration::from_secs(10))
+        .unwrap()
+        .unwrap();
+    assert_eq!(counts["ev_a"], 1);
+    conduit.cancel().unwrap();
+
+    // the connection is usable again
+    conn.ping().unwrap();
+}
+
+#[test]
+fn test_prepare_lazy_and_non_lazy() {
+    let conn_string = test_conn_string("rust-firebird-test-prepare.fdb");
+
+    let mut conn = Connection::create_database(&conn_string).unwrap();
+    conn.execute_batch("CREATE TABLE foo (a INTEGER NOT NULL, b VARCHAR(30), h BLOB SUB_TYPE 1)")
+        .unwrap();
+
+    // same statements whether the server deferred the responses or not
+    for accept_type in [PTYPE_LAZY_SEND, PTYPE_BATCH_SEND] {
+        conn.wp.borrow_mut().accept_type = accept_type;
+        assert_eq!(conn.uses_lazy_send(), accept_type == PTYPE_LAZY_SEND);
+
+        conn.execute("delete from foo", ()).unwrap();
+        conn.execute("insert into foo(a, b) values (?, ?)", (1, "a"))
+            .unwrap();
+        let mut stmt = conn.prepare("select a, b from foo").unwrap();
+        let rows: Vec<(i32, String)> = stmt
+            .query_map((), |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(rows, vec![(1, "a".to_string())]);
+
+        // the cursor is already released, the statement can be run again
+        stmt.close_cursor();
+        assert_eq!(stmt.query(()).unwrap().count(), 1);
+        drop(stmt);
+
+        // blob close responses are deferred too
+        conn.execute("update foo set h = ?", ("blob",)).unwrap();
+        let mut stmt = conn.prepare("select h, a from foo").unwrap();
+        let row = stmt.query(()).unwrap().next().unwrap();
+        let h: String = row.get(0).unwrap();
+        let a: i32 = row.get(1).unwrap();
+        assert_eq!((h.as_str(), a), ("blob", 1));
+        drop(stmt);
+
+        let mut trans = conn.transaction().unwrap();
+        trans
+            .execute("insert into foo(a, b) values (?, ?)", (2, "b"))
+            .unwrap();
+        let mut stmt = trans.prepare("select count(*) from foo").unwrap();
+        let count: i32 = stmt.query(()).unwrap().next().unwrap().get(0).unwrap();
+        assert_eq!(count, 2);
+        drop(stmt);
+        trans.rollback().unwrap();
+    }
+}
+
+#[test]
+fn test_query_in() {
+    let conn_string = test_conn_string("rust-firebird-test-query-in.fdb");
+
+    let mut conn = Connection::create_database(&conn_string).unwrap();
+    conn.execute_batch("CREATE TABLE foo (a INTEGER NOT NULL)")
+        .unwrap();
+    {
+        let mut stmt = conn.prepare("insert into foo(a) values (?)").unwrap();
+        let rowcount = stmt.execute_all((1..=5).map(|a| (a,))).unwrap();
+        assert_eq!(rowcount, 5);
+    }
+
+    let values = [Param::from(2), Param::from(4), Param::from(5)];
+    let rows: Vec<i32> = conn
+        .query_in("select a from foo where a in ", &values, " order by a")
+        .unwrap()
+        .map(|row| row.get(0).unwrap())
+        .collect();
+    assert_eq!(rows, vec![2, 4, 5]);
+
+    let rows = conn
+        .query_in("select a from foo where a in ", &[], "")
+        .unwrap();
+    assert_eq!(rows.count(), 0);
+}
+
+#[test]
+fn test_fetch_blobs() {
+    let conn_string = test_conn_string("rust-firebird-test-fetch-blobs.fdb");
+
+    let mut conn = Connection::create_database(&conn_string).unwrap();
+    conn.execute_batch("CREATE TABLE foo (a INTEGER NOT NULL, h BLOB SUB_TYPE 1)")
+        .unwrap();
+    conn.execute("insert into foo(a, h) values (?, ?)", (1, "This is a pen"))
+        .unwrap();
+
+    let mut stmt = conn.prepare("select h from foo").unwrap();
+    stmt.set_fetch_blobs(false);
+    let blob_id: BlobId = stmt.query(()).unwrap().next().unwrap().get(0).unwrap();
+    assert_eq!(stmt.get_blob(&blob_id).unwrap(), b"This is a pen".to_vec());
+
+    // the same blob bound to another row
+    drop(stmt);
+    conn.execute("insert into foo(a, h) values (?, ?)", (2, blob_id))
+        .unwrap();
+    let mut stmt = conn.prepare("select h from foo where a = 2").unwrap();
+    let h: Vec<u8> = stmt.query(()).unwrap().next().unwrap().get(0).unwrap();
+    assert_eq!(h, b"This is a pen".to_vec());
+}
+
+#[test]
+fn test_blob_reader() {
+    let conn_string = test_conn_string("rust-firebird-test-blob-reader.fdb");
+
+    let mut conn = Connection::create_database(&conn_string).unwrap();
+    conn.execute_batch("CREATE TABLE docs (data BLOB SUB_TYPE 0)")
+        .unwrap();
+    // more than one segment
+    let data: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8).collect();
+    conn.execute("insert into docs(data) values (?)", (data.as_slice(),))
+        .unwrap();
+
+    let mut stmt = conn.prepare("select data from docs").unwrap();
+    stmt.set_fetch_blobs(false);
+    let blob_id: BlobId = stmt.query(()).unwrap().next().unwrap().get(0).unwrap();
+
+    // small reads across the segment boundaries
+    let mut reader = stmt.blob_reader(&blob_id).unwrap();
+    let mut read = Vec::new();
+    let mut chunk = [0u8; 7];
+    loop {
+        let n = reader.read(&mut chunk).unwrap();
+        if n == 0 {
+            break;
+        }
+        read.extend_from_slice(&chunk[..n]);
+    }
+    assert_eq!(reader.read(&mut chunk).unwrap(), 0);
+    drop(reader);
+    assert_eq!(read, data);
+
+    let mut copied = Vec::new();
+    let mut reader = stmt.blob_reader(&blob_id).unwrap();
+    assert_eq!(
+        std::io::copy(&mut reader, &mut copied).unwrap(),
+        data.len() as u64
+    );
+    drop(reader);
+    assert_eq!(copied, data);
+}
+
+#[test]
+fn test_octets() {
+    let conn_string = test_conn_string("rust-firebird-test-octets.fdb");
+
+    let mut conn = Connection::create_database(&conn_string).unwrap();
+    conn.execute_batch(
+        "CREATE TABLE keys (k CHAR(16) CHARACTER SET OCTETS, v VARCHAR(8) CHARACTER SET OCTETS)",
+    )
+    .unwrap();
+    // not valid UTF-8
+    let key: Vec<u8> = (0..16).map(|i| 0xf0 + i as u8).collect();
+    conn.execute(
+        "insert into keys(k, v) values (?, ?)",
+        (
+            Param::Octets(key.clone()),
+            Param::Octets(vec![0xff, 0x00, 0xfe]),
+        ),
+    )
+    .unwrap();
+    conn.execute(
+        "insert into keys(k, v) values (?, ?)",
+        (Param::Octets(vec![1, 2]), Param::Null),
+    )
+    .unwrap();
+
+    let mut stmt = conn.prepare("select k, v from keys where k = ?").unwrap();
+    let row = stmt
+        .query((Param::Octets(key.clone()),))
+        .unwrap()
+        .next()
+        .unwrap();
+    let k: Vec<u8> = row.get(0).unwrap();
+    let v: Vec<u8> = row.get(1).unwrap();
+    assert_eq!(k, key);
+    assert_eq!(v, vec![0xff, 0x00, 0xfe]);
+    drop(stmt);
+
+    // CHAR OCTETS is padded with zero bytes
+    let mut stmt = conn.prepare("select k from keys where v is null").unwrap();
+    let k: Vec<u8> = stmt.query(()).unwrap().next().unwrap().get(0).unwrap();
+    let mut padded = vec![1, 2];
+    padded.resize(16, 0);
+    assert_eq!(k, padded);
+}
+
+#[test]
+fn test_time_fractions() {
+    let conn_string = test_conn_string("rust-firebird-test-time-fractions.fdb");
+
+    let mut conn = Connection::create_database(&conn_string).unwrap();
+    conn.execute_batch("CREATE TABLE foo (a INTEGER NOT NULL, t TIME, ts TIMESTAMP)")
+        .unwrap();
+
+    // stored in 1/10000 second, finer fractions are truncated
+    let nanos = [0, 100_000_000, 10_000_000, 1_000_000, 100_000, 123_456_789];
+    for (i, nano) in nanos.iter().enumerate() {
+        let t = NaiveTime::from_hms_nano_opt(13, 14, 15, *nano).unwrap();
+        let ts = NaiveDate::from_ymd_opt(2024, 2, 29).unwrap().and_time(t);
+        conn.execute(
+            "insert into foo(a, t, ts) values (?, ?, ?)",
+            (i as i32, t, Param::from(ts)),
+        )
+        .unwrap();
+    }
+
+    let mut stmt = conn.prepare("select t, ts from foo order by a").unwrap();
+    let rows: Vec<(NaiveTime, NaiveDateTime)> = stmt
+        .query(())
+        .unwrap()
+        .map(|row| (row.get(0).unwrap(), row.get(1).unwrap()))
+        .collect();
+    for ((t, ts), nano) in rows.iter().zip(nanos) {
+        let expected = NaiveTime::from_hms_nano_opt(13, 14, 15, nano / 100_000 * 100_000).unwrap();
+        assert_eq!(*t, expected);
+        assert_eq!(ts.time(), expected);
+    }
+}
+
+#[test]
+fn test_blob_file() {
+    let conn_string = test_conn_string("rust-firebird-test-blob-file.fdb");
+
+    let mut conn = Connection::create_database(&conn_string).unwrap();
+    conn.execute_batch("CREATE TABLE docs (data BLOB SUB_TYPE 0)")
+        .unwrap();
+
+    // larger than one blob segment
+    let content: Vec<u8> = (0..100000).map(|i| (i % 251) as u8).collect();
+    let path = env::temp_dir().join("rust-firebird-test-blob-file.bin");
+    std::fs::write(&path, &content).unwrap();
+
+    conn.execute("insert into docs(data) values (?)", (blob_file(&path),))
+        .unwrap();
+    {
+        let mut stmt = conn.prepare("select data from docs").unwrap();
+        let data: Vec<u8> = stmt.query(()).unwrap().next().unwrap().get(0).unwrap();
+        assert_eq!(data, content);
+    }
+
+    assert!(conn
+        .execute(
+            "insert into docs(data) values (?)",
+            (blob_file("/nonexistent/blob.bin"),)
+        )
+        .is_err());
+}
+
+#[test]
+fn test_char_padding() {
+    let conn_string = test_conn_string("rust-firebird-test-char-padding.fdb");
+
+    let mut conn = Connection::create_database(&conn_string).unwrap();
+    conn.execute_batch("CREATE TABLE foo (c CHAR(10) CHARACTER SET UTF8)")
+        .unwrap();
+    conn.execute("insert into foo(c) values (?)", ("日本語",))
+        .unwrap();
+
+    let mut stmt = conn.prepare("select c from foo").unwrap();
+    assert_eq!(stmt.metadata().precision(0), Some(10));
+    let c: String = stmt.query(()).unwrap().next().unwrap().get(0).unwrap();
+    assert_eq!(c, "日本語");
+
+    stmt.set_char_padding(true);
+    let c: String = stmt.query(()).unwrap().next().unwrap().get(0).unwrap();
+    assert_eq!(c, "日本語       ");
+    assert_eq!(c.chars().count(), 10);
+}
+
+#[cfg(feature = "unstable")]
+#[test]
+fn test_with_wire_protocol() {
+    let conn_string = test_conn_string("rust-firebird-test-wire-protocol.fdb");
+
+    let mut conn = Connection::create_database(&conn_string).unwrap();
+    conn.with_wire_protocol(|wp| {
+        wp.op_ping()?;
+        wp.op_response()
+    })
+    .unwrap();
+    assert!(conn.is_valid());
+}
+
+#[test]
+fn test_assert_read_only() {
+    let conn_string = test_conn_string("rust-firebird-test-read-only-stmt.fdb");
+
+    let mut conn = Connection::create_database(&conn_string).unwrap();
+    conn.execute_batch("CREATE TABLE foo (a INTEGER NOT NULL)")
+        .unwrap();
+
+    {
+        let stmt = conn.prepare("select a from foo").unwrap();
+        assert!(stmt.is_select());
+        assert!(stmt.assert_read_only().is_ok());
+    }
+    for query in [
+        "insert into foo(a) values (1)",
+        "update foo set a = 2",
+        "delete from foo",
+        "select a from foo for update",
+        "create table bar (a integer)",
+    ] {
+        let stmt = conn.prepare(query).unwrap();
+        assert!(!stmt.is_select());
+        assert!(stmt.assert_read_only().is_err());
+    }
+}
+
+#[test]
+fn test_int128() {
+    let conn_string = test_conn_string("rust-firebird-test-int128.fdb");
+
+    let mut conn = Connection::create_database(&conn_string).unwrap();
+    conn.execute_batch("CREATE TABLE foo (a INT128)").unwrap();
+    conn.execute("insert into foo(a) values (?)", (i128::MAX,))
+        .unwrap();
+    conn.execute("insert into foo(a) values (?)", (i128::MIN,))
+        .unwrap();
+
+    let mut stmt = conn.prepare("select a from foo order by a").unwrap();
+    let values: Vec<i128> = stmt
+        .query(())
+        .unwrap()
+        .map(|row| row.get(0).unwrap())
+        .collect();
+    assert_eq!(values, vec![i128::MIN, i128::MAX]);
+}
+
+#[cfg(feature = "bigdecimal")]
+#[test]
+fn test_bigdecimal() {
+    use bigdecimal::BigDecimal;
+    use std::str::FromStr;
+
+    let conn_string = test_conn_string("rust-firebird-test-bigdecimal.fdb");
+
+    let mut conn = Connection::create_database(&conn_string).unwrap();
+    conn.execute_batch("CREATE TABLE foo (a INTEGER, b NUMERIC(38, 4))")
+        .unwrap();
+    let values = [
+        BigDecimal::from_str("1234567890123456789012345678901234.5678").unwrap(),
+        BigDecimal::from_str("-9999999999999999999999999999999999.9999").unwrap(),
+        BigDecimal::from_str("0.1234").unwrap(),
+    ];
+    for (i, v) in values.iter().enumerate() {
+        conn.execute("insert into foo(a, b) values (?, ?)", (i as i32, v.clone()))
+            .unwrap();
+    }
+
+    let (_, rows) = conn.query_all("select b from foo order by a", ()).unwrap();
+    let fetched: Vec<BigDecimal> = rows.iter().map(|row| row.get(0).unwrap()).collect();
+    assert_eq!(fetched, values);
+    assert_eq!(
+        rows[0].get::<CellValue>(0).unwrap(),
+        CellValue::Scaled(12345678901234567890123456789012345678, -4)
+    );
+}
+
+#[test]
+fn test_reset_transaction() {
+    let conn_string = test_conn_string("rust-firebird-test-reset-transaction.fdb");
+
+    let mut conn = Connection::create_database(&conn_string).unwrap();
+    conn.execute_batch("CREATE TABLE foo (a INTEGER NOT NULL PRIMARY KEY)")
+        .unwrap();
+    conn.execute("insert into foo(a) values (?)", (1,)).unwrap();
+    assert!(conn.execute("insert into foo(a) values (?)", (1,)).is_err());
+
+    conn.reset_transaction().unwrap();
+    conn.execute("insert into foo(a) values (?)", (2,)).unwrap();
+    let mut stmt = conn.prepare("select count(*) from foo").unwrap();
+    let count: i64 = stmt.query(()).unwrap().next().unwrap().get(0).unwrap();
+    assert_eq!(count, 2);
+}
+
+#[test]
+fn test_execute_script() {
+    let conn_string = test_conn_string("rust-firebird-test-execute-script.fdb");
+
+    let mut conn = Connection::create_database(&conn_string).unwrap();
+    conn.execute_script(
+        r#"
+        -- migration
+        CREATE TABLE foo (a INTEGER NOT NULL, b VARCHAR(30));
+        CREATE GENERATOR foo_seq;
+        COMMIT;
+
+        SET TERM ^ ;
+        CREATE TRIGGER foo_bi FOR foo BEFORE INSERT AS
+        BEGIN
+            NEW.a = GEN_ID(foo_seq, 1);
+        END^
+        SET TERM ; ^
+        COMMIT;
+
+        INSERT INTO foo (a, b) VALUES (0, 'x;y');
+        INSERT INTO foo (a, b) VALUES (0, 'z');
+        "#,
+    )
+    .unwrap();
+
+    let mut stmt = conn.prepare("select a, b from foo order by a").unwrap();
+    let rows: Vec<(i32, String)> = stmt
+        .query(())
+        .unwrap()
+        .map(|row| (row.get(0).unwrap(), row.get(1).unwrap()))
+        .collect();
+    assert_eq!(rows, vec![(1, "x;y".to_string()), (2, "z".to_string())]);
+}
+
+#[test]
+fn test_commit_hard() {
+    let conn_string = test_conn_string("rust-firebird-test-commit-hard.fdb");
+
+    let mut conn = Connection::create_database(&conn_string).unwrap();
+    conn.execute_batch("CREATE TABLE foo (a INTEGER NOT NULL)")
+        .unwrap();
+
+    // the next statement runs on the new transaction
+    conn.commit_hard().unwrap();
+    conn.execute("insert into foo(a) values (?)", (1,)).unwrap();
+    conn.rollback_hard().unwrap();
+    conn.execute("insert into foo(a) values (?)", (2,)).unwrap();
+
+    conn.set_execution_mode(ExecutionMode::Manual).unwrap();
+    conn.execute("insert into foo(a) values (?)", (3,)).unwrap();
+    conn.rollback_hard().unwrap();
+    conn.execute("insert into foo(a) values (?)", (4,)).unwrap();
+    conn.commit_hard().unwrap();
+
+    let mut stmt = conn.prepare("select a from foo order by a").unwrap();
+    let values: Vec<i32> = stmt
+        .query(())
+        .unwrap()
+        .map(|row| row.get(0).unwrap())
+        .collect();
+    assert_eq!(values, vec![1, 2, 4]);
+}
+
+#[test]
+fn test_reset_session() {
+    let user = test_user();
+    let conn_string = test_conn_string("rust-firebird-test-reset-session.fdb");
+
+    let mut conn = Connection::create_database(&conn_string).unwrap();
+    conn.execute(
+        "select rdb$set_context('USER_SESSION', 'foo', 'bar') from rdb$database",
+        (),
+    )
+    .unwrap();
+    let query = "select rdb$get_context('USER_SESSION', 'foo') from rdb$database";
+    {
+        let mut stmt = conn.prepare(query).unwrap();
+        let v: Option<String> = stmt.query(()).unwrap().next().unwrap().get(0).unwrap();
+        assert_eq!(v, Some("bar".to_string()));
+    }
+
+    conn.reset_session().unwrap();
+    {
+        let mut stmt = conn.prepare(query).unwrap();
+        let v: Option<String> = stmt.query(()).unwrap().next().unwrap().get(0).unwrap();
+        assert_eq!(v, None);
+    }
+
+    assert_eq!(conn.current_user().unwrap(), user.to_uppercase());
+    assert_eq!(conn.current_role().unwrap(), "NONE");
+}
+
+#[test]
+fn test_transaction_id() {
+    let conn_string = test_conn_string("rust-firebird-test-transaction-id.fdb");
+
+    let mut conn = Connection::create_database(&conn_string).unwrap();
+    let query =
+        "select cast(rdb$get_context('SYSTEM', 'TRANSACTION_ID') as bigint) from rdb$database";
+    let conn_id = conn.transaction_id().unwrap();
+    {
+        let mut stmt = conn.prepare(query).unwrap();
+        let id: i64 = stmt.query(()).unwrap().next().unwrap().get(0).unwrap();
+        assert_eq!(id as u64, conn_id);
+    }
+
+    let mut trans = conn.transaction().unwrap();
+    let trans_id = trans.id().unwrap();
+    assert_ne!(trans_id, conn_id);
+    let mut stmt = trans.prepare(query).unwrap();
+    let id: i64 = stmt.query(()).unwrap().next().unwrap().get(0).unwrap();
+    assert_eq!(id as u64, trans_id);
+}
+
+#[test]
+fn test_attachment_id() {
+    let conn_string = test_conn_string("rust-firebird-test-attachment-id.fdb");
+
+    let mut conn = Connection::create_database(&conn_string).unwrap();
+    let attachment_id = conn.attachment_id().unwrap();
+    let id: i64 = conn
+        .prepare("select current_connection from rdb$database")
+        .unwrap()
+        .query_row(())
+        .unwrap()
+        .get(0)
+        .unwrap();
+    assert_eq!(id, attachment_id);
+}
+
+#[test]
+fn test_select_null() {
+    let conn_string = test_conn_string("rust-firebird-test-select-null.fdb");
+
+    let mut conn = Connection::create_database(&conn_string).unwrap();
+    let mut stmt = conn.prepare("select null, 1 from rdb$database").unwrap();
+    let row = stmt.query(()).unwrap().next().unwrap();
+    let a: Option<i32> = row.get(0).unwrap();
+    let b: i32 = row.get(1).unwrap();
+    assert_eq!(a, None);
+    assert_eq!(b, 1);
+}
+
+#[test]
+fn test_query_partial() {
+    let conn_string = test_conn_string("rust-firebird-test-query-partial.fdb");
+
+    let mut conn = Connection::create_database(&conn_string).unwrap();
+    conn.execute_batch("CREATE TABLE foo (a INTEGER NOT NULL)")
+        .unwrap();
+    {
+        let mut stmt = conn.prepare("insert into foo(a) values (?)").unwrap();
+        stmt.execute_all((1..=10).map(|i| (i,))).unwrap();
+    }
+
+    // division by zero at a = 5
+    let query = "select 10 / (5 - a) from foo order by a";
+    let mut stmt = conn.prepare(query).unwrap();
+    assert!(stmt.query(()).is_err());
+
+    let results: Vec<_> = stmt.query_partial(()).unwrap().collect();
+    let (last, rows) = results.split_last().unwrap();
+    assert!(last.is_err());
+    assert!(rows.len() <= 4);
+    for (i, row) in rows.iter().enumerate() {
+        let v: i64 = row.as_ref().unwrap().get(0).unwrap();
+        assert_eq!(v, 10 / (4 - i as i64));
+    }
+}
+
+#[test]
+fn test_prepared_ddl() {
+    let conn_string = test_conn_string("rust-firebird-test-prepared-ddl.fdb");
+
+    let mut conn = Connection::create_database(&conn_string).unwrap();
+    {
+        let mut stmt = conn
+            .prepare("create table foo (a integer not null)")
+            .unwrap();
+        assert!(stmt.is_ddl());
+        assert!(!stmt.is_select());
+        stmt.execute(()).unwrap();
+    }
+    conn.execute("insert into foo(a) values (?)", (1,)).unwrap();
+
+    // the table is visible from another connection
+    let mut conn2 = Connection::connect(&conn_string).unwrap();
+    let mut stmt = conn2.prepare("select a from foo").unwrap();
+    assert!(!stmt.is_ddl());
+    let rows: Vec<i32> = stmt
+        .query(())
+        .unwrap()
+        .map(|row| row.get(0).unwrap())
+        .collect();
+    assert_eq!(rows, vec![1]);
+}
+
+#[test]
+fn test_generator_values() {
+    let conn_string = test_conn_string("rust-firebird-test-generator-values.fdb");
+
+    let mut conn = Connection::create_database(&conn_string).unwrap();
+    conn.execute_batch("CREATE SEQUENCE g1").unwrap();
+    conn.execute_batch("CREATE SEQUENCE g2").unwrap();
+    conn.execute_batch("SET GENERATOR g2 TO 100").unwrap();
+    conn.execute_batch("CREATE SEQUENCE \"Mixed g3\" START WITH 7")
+        .unwrap();
+    conn.execute("SELECT NEXT VALUE FOR g1 FROM RDB$DATABASE", ())
+        .unwrap();
+
+    assert_eq!(conn.generator_values(&["g1", "g2"]).unwrap(), vec![1, 100]);
+    assert_eq!(conn.generator_values(&[]).unwrap(), Vec::<i64>::new());
+    assert!(conn.generator_values(&["no_such_generator"]).is_err());
+    assert_eq!(conn.generator_values(&["\"Mixed g3\""]).unwrap().len(), 1);
+    // names are quoted, not pasted into the query
+    assert!(conn
+        .generator_values(&["g1, 0) FROM RDB$DATABASE --"])
+        .is_err());
+}
+
+#[test]
+fn test_set_role() {
+    let user = test_user();
+    let conn_string = test_conn_string("rust-firebird-test-set-role.fdb");
+
+    let mut conn = Connection::create_database(&conn_string).unwrap();
+    conn.execute_batch("CREATE ROLE tenant1").unwrap();
+    conn.execute_batch(&format!("GRANT tenant1 TO {}", user))
+        .unwrap();
+    assert_eq!(conn.current_role().unwrap(), "NONE");
+
+    conn.set_role("tenant1").unwrap();
+    assert_eq!(conn.current_role().unwrap(), "TENANT1");
+
+    conn.reset_session().unwrap();
+    assert_eq!(conn.current_role().unwrap(), "NONE");
+
+    assert!(conn.set_role("no_such_role").is_err());
+    assert!(conn.set_role("tenant1; DROP ROLE tenant1").is_err());
+    conn.set_role("\"TENANT1\"").unwrap();
+    assert_eq!(conn.current_role().unwrap(), "TENANT1");
+}
+
+#[test]
+fn test_decfloat_param() {
+    let conn_string = test_conn_string("rust-firebird-test-decfloat-param.fdb");
+
+    let mut conn = Connection::create_database(&conn_string).unwrap();
+    conn.execute_batch("CREATE TABLE foo (a DECFLOAT(16), b DECFLOAT(34))")
+        .unwrap();
+    conn.execute(
+        "insert into foo(a, b) values (?, ?)",
+        (
+            Param::DecFloat16(dec!(-123.45)),
+            Param::DecFloat34(dec!(12345678901234567890.123456789)),
+        ),
+    )
+    .unwrap();
+
+    let mut stmt = conn
+        .prepare("select cast(a as varchar(50)), cast(b as varchar(50)) from foo")
+        .unwrap();
+    let row = stmt.query(()).unwrap().next().unwrap();
+    let a: String = row.get(0).unwrap();
+    let b: String = row.get(1).unwrap();
+    assert_eq!(a, "-123.45");
+    assert_eq!(b, "12345678901234567890.123456789");
+}
+
+#[test]
+fn test_query_all() {
+    let conn_string = test_conn_string("rust-firebird-test-query-all.fdb");
+
+    let mut conn = Connection::create_database(&conn_string).unwrap();
+    conn.execute_batch("CREATE TABLE foo (a INTEGER NOT NULL, b VARCHAR(30))")
+        .unwrap();
+    conn.execute("insert into foo(a, b) values (?, ?)", (1, "a"))
+        .unwrap();
+    conn.execute("insert into foo(a, b) values (?, ?)", (2, "b"))
+        .unwrap();
+
+    let (metadata, rows) = conn
+        .query_all("select a, b from foo where a >= ? order by a", (1,))
+        .unwrap();
+    assert_eq!(metadata.column_count(), 2);
+    assert_eq!(metadata.column_name(1), Some("B"));
+    assert_eq!(rows.len(), 2);
+    let b: String = rows[1].get(1).unwrap();
+    assert_eq!(b, "b");
+
+    // the connection is usable again, no statement is left borrowed
+    conn.execute("delete from foo", ()).unwrap();
+}
+
+#[test]
+fn test_blob_inline_threshold() {
+    let conn_string = test_conn_string("rust-firebird-test-blob-inline-threshold.fdb");
+
+    let mut conn = Connection::create_database(&conn_string).unwrap();
+    conn.execute_batch("CREATE TABLE foo (a INTEGER NOT NULL, h BLOB SUB_TYPE 1)")
+        .unwrap();
+    let long_text = "x".repeat(1000);
+    conn.execute("insert into foo(a, h) values (?, ?)", (1, "short"))
+        .unwrap();
+    conn.execute(
+        "insert into foo(a, h) values (?, ?)",
+        (2, long_text.as_str()),
+    )
+    .unwrap();
+
+    let mut stmt = conn.prepare("select h from foo order by a").unwrap();
+    stmt.set_blob_inline_threshold(100);
+    let mut rows = stmt.query(()).unwrap();
+    let h: String = rows.next().unwrap().get(0).unwrap();
+    assert_eq!(h, "short");
+    let cell: CellValue = rows.next().unwrap().get(0).unwrap();
+    match cell {
+        CellValue::BlobId(blob_id) => {
+            assert_eq!(stmt.get_blob(&blob_id).unwrap(), long_text.as_bytes());
+        }
+        _ => panic!("not a blob id: {:?}", cell),
+    }
+}
+
+#[test]
+fn test_execution_mode() {
+    let conn_string = test_conn_string("rust-firebird-test-execution-mode.fdb");
+
+    let mut conn = Connection::create_database(&conn_string).unwrap();
+    conn.execute_batch("CREATE TABLE foo (a INTEGER NOT NULL)")
+        .unwrap();
+    assert_eq!(conn.execution_mode(), ExecutionMode::AutoCommit);
+
+    let mut conn2 = Connection::connect(&conn_string).unwrap();
+    let count = |conn: &mut Connection| -> i32 {
+        let mut stmt = conn.prepare("select count(*) from foo").unwrap();
+        let row = stmt.query(()).unwrap().next().unwrap();
+        row.get(0).unwrap()
+    };
+
+    // committed as executed
+    conn.execute("insert into foo(a) values (1)", ()).unwrap();
+    conn2.commit().unwrap(); // new snapshot
+    assert_eq!(count(&mut conn2), 1);
+
+    conn.set_execution_mode(ExecutionMode::Manual).unwrap();
+    assert_eq!(conn.execution_mode(), ExecutionMode::Manual);
+    conn.execute("insert into foo(a) values (2)", ()).unwrap();
+    conn.execute_batch("insert into foo(a) values (3)").unwrap();
+    assert_eq!(count(&mut conn), 3);
+    conn.rollback().unwrap();
+    assert_eq!(count(&mut conn), 1);
+
+    conn.execute("insert into foo(a) values (2)", ()).unwrap();
+    conn.commit().unwrap();
+    conn2.commit().unwrap(); // new snapshot
+    assert_eq!(count(&mut conn2), 2);
+
+    // statements in a transaction wait for its commit
+    conn.set_execution_mode(ExecutionMode::AutoCommit).unwrap();
+    {
+        let mut trans = conn.transaction().unwrap();
+        trans.execute_batch("delete from foo").unwrap();
+        trans.execute("insert into foo(a) values (9)", ()).unwrap();
+        trans.rollback().unwrap();
+    }
+    assert_eq!(count(&mut conn), 2);
+}
+
+#[test]
+fn test_transaction_execute_batch() {
+    let conn_string = test_conn_string("rust-firebird-test-transaction-execute-batch.fdb");
+
+    let mut conn = Connection::create_database(&conn_string).unwrap();
+    conn.execute_batch("CREATE TABLE foo (a INTEGER NOT NULL)")
+        .unwrap();
+
+    let mut conn2 = Connection::connect(&conn_string).unwrap();
+    let count = |conn: &mut Connection| -> i32 {
+        let mut stmt = conn.prepare("select count(*) from foo").unwrap();
+        let row = stmt.query(()).unwrap().next().unwrap();
+        row.get(0).unwrap()
+    };
+
+    // execute_batch in a transaction is not committed as it is executed
+    let mut trans = conn.transaction().unwrap();
+    trans
+        .execute_batch("insert into foo(a) values (1)")
+        .unwrap();
+    conn2.commit().unwrap(); // new snapshot
+    assert_eq!(count(&mut conn2), 0);
+    trans.commit().unwrap();
+    conn2.commit().unwrap(); // new snapshot
+    assert_eq!(count(&mut conn2), 1);
+}
+
+#[test]
+fn test_sql_rows() {
+    let conn_string = test_conn_string("rust-firebird-test-sql-rows.fdb");
+
+    let mut conn = Connection::create_database(&conn_string).unwrap();
+    conn.execute_batch("CREATE TABLE foo (a INTEGER NOT NULL)")
+        .unwrap();
+    {
+        let mut stmt = conn.prepare("insert into foo(a) values (?)").unwrap();
+        stmt.execute_all((1..=25).map(|i| (i,))).unwrap();
+    }
+
+    let page = |conn: &mut Connection, offset: usize, limit: usize| -> Vec<i32> {
+        let query = format!("select a from foo order by a {}", sql_rows(offset, limit));
+        let mut stmt = conn.prepare(&query).unwrap();
+        stmt.query(())
+            .unwrap()
+            .map(|row| row.get(0).unwrap())
+            .collect()
+    };
+    assert_eq!(page(&mut conn, 0, 10), (1..=10).collect::<Vec<i32>>());
+    assert_eq!(page(&mut conn, 20, 10), (21..=25).collect::<Vec<i32>>());
+    assert_eq!(page(&mut conn, 30, 10), Vec::<i32>::new());
+}
@@ -27,6 +27,24 @@ use std::env;
 use std::str::FromStr;
 use urlencoding;
 
+// url of the test database /tmp/{db_name}, which may be followed by ?options
+fn test_conn_string(db_name: &str) -> String {
+    let user = match env::var("ISC_USER") {
+        Ok(val) => val,
+        Err(_) => "sysdba".to_string(),
+    };
+    let password = match env::var("ISC_PASSWORD") {
+        Ok(val) => val,
+        Err(_) => "masterkey".to_string(),
+    };
+    format!(
+        "firebird://{}:{}@localhost/tmp/{}",
+        &user,
+        urlencoding::encode(&password),
+        db_name
+    )
+}
+
 #[derive(PartialEq, Debug)]
 struct TzTest {
     id: i32,
@@ -149,3 +167,74 @@ fn test_timezone() {
         assert_eq!(r, expects[i]);
     }
 }
+
+#[test]
+#[ignore]
+fn test_timestamp_tz_param() {
+    let conn_string =
+        test_conn_string("rust-firebird-test-timestamp-tz-param.fdb?timezone=Asia/Tokyo");
+    let mut conn = Connection::create_database(&conn_string).unwrap();
+    conn.execute_batch("CREATE TABLE tz_test (id INTEGER NOT NULL, ts TIMESTAMP WITH TIME ZONE)")
+        .unwrap();
+
+    let values: Vec<chrono::DateTime<chrono_tz::Tz>> = ["Asia/Seoul", "UTC", "Asia/Kolkata"]
+        .iter()
+        .map(|name| {
+            chrono_tz::Tz::from_str(name)
+                .unwrap()
+                .from_local_datetime(
+                    &NaiveDate::from_ymd_opt(1967, 8, 11)
+                        .unwrap()
+                        .and_hms_opt(23, 45, 1)
+                        .unwrap(),
+                )
+                .unwrap()
+        })
+        .collect();
+    for (i, ts) in values.iter().enumerate() {
+        conn.execute(
+            "insert into tz_test (id, ts) values (?, ?)",
+            (i as i32, *ts),
+        )
+        .unwrap();
+    }
+
+    let mut stmt = conn.prepare("select ts from tz_test order by id").unwrap();
+    let fetched: Vec<chrono::DateTime<chrono_tz::Tz>> = stmt
+        .query(())
+        .unwrap()
+        .map(|row| row.get(0).unwrap())
+        .collect();
+    assert_eq!(fetched, values);
+}
+
+#[test]
+#[ignore]
+fn test_time_tz_param() {
+    let conn_string = test_conn_string("rust-firebird-test-time-tz-param.fdb?timezone=Asia/Tokyo");
+    let mut conn = Connection::create_database(&conn_string).unwrap();
+    conn.execute_batch("CREATE TABLE tz_test (id INTEGER NOT NULL, t TIME WITH TIME ZONE)")
+        .unwrap();
+
+    let values: Vec<(NaiveTime, chrono_tz::Tz)> = ["Asia/Seoul", "UTC", "Asia/Kolkata"]
+        .iter()
+        .map(|name| {
+            (
+                NaiveTime::from_hms_opt(12, 34, 56).unwrap(),
+                chrono_tz::Tz::from_str(name).unwrap(),
+            )
+        })
+        .collect();
+    for (i, t) in values.iter().enumerate() {
+        conn.execute("insert into tz_test (id, t) values (?, ?)", (i as i32, *t))
+            .unwrap();
+    }
+
+    let mut stmt = conn.prepare("select t from tz_test order by id").unwrap();
+    let fetched: Vec<(NaiveTime, chrono_tz::Tz)> = stmt
+        .query(())
+        .unwrap()
+        .map(|row| row.get(0).unwrap())
+        .collect();
+    assert_eq!(fetched, values);
+}
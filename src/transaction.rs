@@ -36,14 +36,20 @@ impl Transaction<'_> {
         Ok(Transaction { conn, trans_handle })
     }
 
+    // not committed until commit(), whatever the connection execution mode
     pub fn execute_batch(&mut self, query: &str) -> Result<(), Error> {
         self.conn._execute_batch(query, self.trans_handle)
     }
 
-    pub fn execute<P: Params>(&mut self, query: &str, params: P) -> Result<(), Error> {
+    pub fn execute<P: Params>(&mut self, query: &str, params: P) -> Result<usize, Error> {
         self.conn._execute(query, params, self.trans_handle)
     }
 
+    // server side transaction id
+    pub fn id(&self) -> Result<u64, Error> {
+        self.conn._transaction_id(self.trans_handle)
+    }
+
     pub fn commit(&mut self) -> Result<(), Error> {
         self.conn._commit(self.trans_handle)
     }
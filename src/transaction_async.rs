@@ -25,27 +25,36 @@ use super::params::Params;
 use super::statement_async::StatementAsync;
 use super::ConnectionAsync;
 
-use async_std::task;
-
 pub struct TransactionAsync<'conn> {
     conn: &'conn mut ConnectionAsync,
     trans_handle: i32,
+    closed: bool,
 }
 
 impl TransactionAsync<'_> {
     pub async fn new(conn: &mut ConnectionAsync) -> Result<TransactionAsync, Error> {
         let trans_handle = conn._begin_trans().await?;
-        Ok(TransactionAsync { conn, trans_handle })
+        Ok(TransactionAsync {
+            conn,
+            trans_handle,
+            closed: false,
+        })
     }
 
+    // not committed until commit(), whatever the connection execution mode
     pub async fn execute_batch(&mut self, query: &str) -> Result<(), Error> {
         self.conn._execute_batch(query, self.trans_handle).await
     }
 
-    pub async fn execute<P: Params>(&mut self, query: &str, params: P) -> Result<(), Error> {
+    pub async fn execute<P: Params>(&mut self, query: &str, params: P) -> Result<usize, Error> {
         self.conn._execute(query, params, self.trans_handle).await
     }
 
+    // server side transaction id
+    pub async fn id(&self) -> Result<u64, Error> {
+        self.conn._transaction_id(self.trans_handle).await
+    }
+
     pub async fn commit(&mut self) -> Result<(), Error> {
         self.conn._commit(self.trans_handle).await
     }
@@ -57,10 +66,20 @@ impl TransactionAsync<'_> {
     pub async fn prepare(&mut self, query: &str) -> Result<StatementAsync, Error> {
         self.conn._prepare(query, self.trans_handle).await
     }
+
+    // end the transaction, what isn't committed is rolled back.
+    // A dropped transaction is rolled back with the next request
+    pub async fn close(mut self) -> Result<(), Error> {
+        self.closed = true;
+        self.conn.drop_transaction(self.trans_handle).await
+    }
 }
 
 impl Drop for TransactionAsync<'_> {
     fn drop(&mut self) {
-        task::block_on(self.conn.drop_transaction(self.trans_handle));
+        // see Drop for WireProtocolAsync
+        if !self.closed {
+            self.conn.defer_rollback(self.trans_handle);
+        }
     }
 }
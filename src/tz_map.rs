@@ -2,8 +2,7 @@
 
 use maplit::hashmap;
 
-#[allow(dead_code)]
-pub fn timezone_id_by_name(s: &str) -> u16 {
+pub fn timezone_id_by_name(s: &str) -> Option<u16> {
     let map = hashmap! {
         "GMT" => 65535,
         "ACT" => 65534,
@@ -641,7 +640,7 @@ pub fn timezone_id_by_name(s: &str) -> u16 {
         "Asia/Qostanay" => 64902,
         "Pacific/Kanton" => 64901,
     };
-    map[s]
+    map.get(s).copied()
 }
 
 pub fn timezone_name_by_id(id: u16) -> &'static str {
@@ -22,6 +22,7 @@
 
 #![allow(dead_code)]
 
+use std::collections::HashMap;
 use std::io::prelude::*;
 use std::mem::transmute;
 use std::str;
@@ -34,7 +35,13 @@ use crypto::sha1::Sha1;
 use hex;
 use num_bigint::{BigInt, BigUint, Sign};
 
+use super::error::{ParamError, ValueError};
 use super::tz_map;
+use super::{
+    ISC_INFO_ATTACHMENT_ID, ISC_INFO_BLOB_TOTAL_LENGTH, ISC_INFO_END, ISC_INFO_REQ_DELETE_COUNT,
+    ISC_INFO_REQ_INSERT_COUNT, ISC_INFO_REQ_SELECT_COUNT, ISC_INFO_REQ_UPDATE_COUNT,
+    ISC_INFO_SQL_STMT_SELECT, ISC_INFO_TRA_ID, ISC_INFO_TRUNCATED,
+};
 
 pub fn int32_to_bytes(i: i32) -> [u8; 4] {
     // little endian i32 to Vec<u8>
@@ -83,6 +90,11 @@ pub fn bytes_to_str(b: &[u8]) -> String {
     str::from_utf8(b).unwrap().to_string()
 }
 
+// invalid UTF-8 sequences are replaced, for server messages in other charsets
+pub fn bytes_to_lossy_str(b: &[u8]) -> String {
+    String::from_utf8_lossy(b).into_owned()
+}
+
 pub fn bytes_to_rtrim_str(b: &[u8]) -> String {
     str::from_utf8(b).unwrap().trim_end().to_string()
 }
@@ -131,6 +143,18 @@ pub fn bytes_to_buint16(b: &[u8]) -> u16 {
     ((b[0] as u16) << 8) + ((b[1] as u16) << 0)
 }
 
+pub fn bytes_to_int(b: &[u8]) -> i64 {
+    // little endian variable length integer (isc_vax_integer)
+    let mut n: i64 = 0;
+    for (i, c) in b.iter().enumerate() {
+        n += (*c as i64) << (8 * i);
+    }
+    if !b.is_empty() && b.len() < 8 && b[b.len() - 1] & 0x80 != 0 {
+        n -= 1 << (8 * b.len());
+    }
+    n
+}
+
 pub fn bytes_to_int64(b: &[u8]) -> i64 {
     let tmp: [u8; 8] = [b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]];
     let v: i64 = unsafe { transmute::<[u8; 8], i64>(tmp) };
@@ -170,6 +194,19 @@ pub fn bytes_to_bint128(b: &[u8]) -> i128 {
     v
 }
 
+// n * 10^scale in decimal notation, (12345, -2) is "123.45"
+pub fn scaled_to_string(n: i128, scale: i32) -> String {
+    let sign = if n < 0 { "-" } else { "" };
+    let digits = n.unsigned_abs().to_string();
+    if scale >= 0 {
+        return format!("{}{}{}", sign, digits, "0".repeat(scale as usize));
+    }
+    let frac_len = (-scale) as usize;
+    let digits = format!("{:0>width$}", digits, width = frac_len + 1);
+    let (int_part, frac_part) = digits.split_at(digits.len() - frac_len);
+    format!("{}{}.{}", sign, int_part, frac_part)
+}
+
 pub fn bytes_to_uint64(b: &[u8]) -> u64 {
     // little endian u64
     ((b[0] as u64) << 0)
@@ -207,7 +244,7 @@ pub fn bytes_to_f64(b: &[u8]) -> f64 {
 }
 
 pub fn bytes_to_naive_date(b: &[u8]) -> chrono::NaiveDate {
-    let mut nday = bytes_to_buint32(b) + 678882;
+    let mut nday = bytes_to_bint32(b) as i64 + 678882;
     let century = (4 * nday - 1) / 146097;
     nday = 4 * nday - 1 - 146097 * century;
     let mut day = nday / 4;
@@ -227,7 +264,7 @@ pub fn bytes_to_naive_date(b: &[u8]) -> chrono::NaiveDate {
         year += 1;
     }
 
-    chrono::NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    chrono::NaiveDate::from_ymd_opt(year, month as u32, day as u32).unwrap()
 }
 
 pub fn bytes_to_naive_time(b: &[u8]) -> chrono::NaiveTime {
@@ -237,7 +274,14 @@ pub fn bytes_to_naive_time(b: &[u8]) -> chrono::NaiveTime {
     let h = m / 60;
     m = m % 60;
     s = s % 60;
-    chrono::NaiveTime::from_hms_micro_opt(h, m, s, (n % 10000) * 100000).unwrap()
+    // fractions are in 1/10000 second
+    chrono::NaiveTime::from_hms_nano_opt(h, m, s, (n % 10000) * 100_000).unwrap()
+}
+
+// Firebird converts TIME WITH TIME ZONE between UTC and a zone on this date,
+// so the offset doesn't depend on the day the value is read or written
+pub fn time_tz_reference_date() -> chrono::NaiveDate {
+    chrono::NaiveDate::from_ymd_opt(2020, 1, 1).unwrap()
 }
 
 pub fn bytes_to_time_tz(b: &[u8]) -> (chrono::NaiveTime, chrono_tz::Tz) {
@@ -256,8 +300,7 @@ pub fn bytes_to_time_tz(b: &[u8]) -> (chrono::NaiveTime, chrono_tz::Tz) {
             .parse()
             .unwrap();
     }
-    let date = chrono::Utc::now().date_naive();
-    let dt = chrono::NaiveDateTime::new(date, time);
+    let dt = chrono::NaiveDateTime::new(time_tz_reference_date(), time);
     let tz_aware = timezone
         .from_local_datetime(&dt)
         .unwrap()
@@ -353,19 +396,29 @@ pub fn bytes_to_blr(b: &[u8]) -> (Vec<u8>, Vec<u8>) {
     (blr, v)
 }
 
-pub fn convert_date(year: i32, month: u32, day: u32) -> [u8; 4] {
+pub fn convert_date(year: i32, month: u32, day: u32) -> Result<[u8; 4], ParamError> {
     // Convert date to BLR format data
-    let i = month + 9;
-    let jy = year as u32 + (i / 12) - 1;
+    // Firebird DATE range is 0001-01-01 .. 9999-12-31
+    if !(1..=9999).contains(&year) {
+        return Err(ParamError::new(&format!(
+            "date out of range: {:04}-{:02}-{:02}",
+            year, month, day
+        )));
+    }
+    let i = month as i64 + 9;
+    let jy = year as i64 + (i / 12) - 1;
     let jm = i % 12;
     let c = jy / 100;
-    let j = (146097 * c) / 4 + (1461 * (jy - 100 * c)) / 4 + (153 * jm + 2) / 5 + day - 678882;
-    bint32_to_bytes(j as i32)
+    let j =
+        (146097 * c) / 4 + (1461 * (jy - 100 * c)) / 4 + (153 * jm + 2) / 5 + day as i64 - 678882;
+    Ok(bint32_to_bytes(j as i32))
 }
 
 pub fn convert_time(hour: u32, minute: u32, second: u32, nanosecond: u32) -> [u8; 4] {
-    // Convert time to BLR format time
-    let n = (hour * 3600 + minute * 60 + second) * 10000 + nanosecond * 10;
+    // Convert time to BLR format time, in 1/10000 second.
+    // Finer fractions are truncated, a leap second stays in the same second
+    let fraction = (nanosecond / 100_000).min(9999);
+    let n = (hour * 3600 + minute * 60 + second) * 10000 + fraction;
     bint32_to_bytes(n as i32)
 }
 
@@ -405,3 +458,581 @@ pub fn guess_wire_crypt(buf: &[u8]) -> (Vec<u8>, Vec<u8>) {
     }
     return (vec![], vec![])
 }
+
+// COMMIT or ROLLBACK without RETAIN ends the transaction it is executed in
+pub fn ends_transaction(query: &str) -> bool {
+    let words: Vec<String> = query
+        .trim()
+        .trim_end_matches(';')
+        .split_whitespace()
+        .map(|w| w.to_uppercase())
+        .collect();
+    words == ["COMMIT"]
+        || words == ["COMMIT", "WORK"]
+        || words == ["ROLLBACK"]
+        || words == ["ROLLBACK", "WORK"]
+}
+
+// isql settings which the server doesn't know, e.g. SET SQL DIALECT 3
+fn is_isql_command(words: &[&str]) -> bool {
+    const ISQL_SETS: [&str; 12] = [
+        "SQL", "NAMES", "AUTODDL", "AUTO", "ECHO", "LIST", "STATS", "COUNT", "PLAN", "PLANONLY",
+        "HEADING", "WARNINGS",
+    ];
+    words.len() >= 2
+        && words[0].eq_ignore_ascii_case("SET")
+        && ISQL_SETS.iter().any(|x| words[1].eq_ignore_ascii_case(x))
+}
+
+// statements of a sql script, separated by ";" or by the terminator set
+// with SET TERM, terminators in quotes or comments don't count.
+// isql only commands (SET SQL DIALECT, SET NAMES, SET AUTODDL ...) are skipped
+pub fn split_sql_script(script: &str) -> Vec<String> {
+    let mut statements: Vec<String> = Vec::new();
+    let mut term = ";".to_string();
+    let mut current = String::new(); // statement text
+    let mut bare = String::new(); // statement text without comments
+    let chars: Vec<char> = script.chars().collect();
+    let mut i: usize = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\'' || c == '"' {
+            let start = i;
+            i += 1;
+            while i < chars.len() {
+                if chars[i] == c {
+                    // doubled quote is an escaped one
+                    if i + 1 < chars.len() && chars[i + 1] == c {
+                        i += 1;
+                    } else {
+                        break;
+                    }
+                }
+                i += 1;
+            }
+            let quoted: String = chars[start..(i + 1).min(chars.len())].iter().collect();
+            current.push_str(&quoted);
+            bare.push_str(&quoted);
+            i += 1;
+        } else if c == '-' && i + 1 < chars.len() && chars[i + 1] == '-' {
+            while i < chars.len() && chars[i] != '\n' {
+                current.push(chars[i]);
+                i += 1;
+            }
+        } else if c == '/' && i + 1 < chars.len() && chars[i + 1] == '*' {
+            let start = i;
+            i += 2;
+            while i < chars.len() && !(chars[i - 1] == '*' && chars[i] == '/' && i > start + 2) {
+                i += 1;
+            }
+            let comment: String = chars[start..(i + 1).min(chars.len())].iter().collect();
+            current.push_str(&comment);
+            bare.push(' ');
+            i += 1;
+        } else if chars[i..].starts_with(&term.chars().collect::<Vec<char>>()) {
+            i += term.chars().count();
+            let words: Vec<&str> = bare.split_whitespace().collect();
+            if words.len() == 3
+                && words[0].eq_ignore_ascii_case("SET")
+                && words[1].eq_ignore_ascii_case("TERM")
+            {
+                term = words[2].to_string();
+            } else if !words.is_empty() && !is_isql_command(&words) {
+                statements.push(current.trim().to_string());
+            }
+            current.clear();
+            bare.clear();
+        } else {
+            current.push(c);
+            bare.push(c);
+            i += 1;
+        }
+    }
+    if !bare.trim().is_empty() {
+        statements.push(current.trim().to_string());
+    }
+    statements
+}
+
+// upper cased words of a query outside parentheses, quotes and comments
+fn top_level_words(query: &str) -> Vec<String> {
+    let mut words: Vec<String> = Vec::new();
+    let mut word = String::new();
+    let mut depth: usize = 0;
+    let chars: Vec<char> = query.chars().collect();
+    let mut i: usize = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_alphanumeric() || c == '_' || c == '$' {
+            word.push(c);
+            i += 1;
+            continue;
+        }
+        if !word.is_empty() {
+            if depth == 0 {
+                words.push(word.to_uppercase());
+            }
+            word.clear();
+        }
+        if c == '\'' || c == '"' {
+            i += 1;
+            while i < chars.len() && chars[i] != c {
+                i += 1;
+            }
+        } else if c == '-' && i + 1 < chars.len() && chars[i + 1] == '-' {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '/' && i + 1 < chars.len() && chars[i + 1] == '*' {
+            i += 2;
+            while i + 1 < chars.len() && !(chars[i] == '*' && chars[i + 1] == '/') {
+                i += 1;
+            }
+            i += 1;
+        } else if c == '(' {
+            depth += 1;
+        } else if c == ')' {
+            depth = depth.saturating_sub(1);
+        }
+        i += 1;
+    }
+    if !word.is_empty() && depth == 0 {
+        words.push(word.to_uppercase());
+    }
+    words
+}
+
+// count of all the rows of a select and the select of limit rows after offset.
+// The select can't limit its rows itself (ROWS, OFFSET, FETCH) nor lock them
+// (FOR UPDATE, WITH LOCK), the page clause is appended to it
+pub fn count_and_page_queries(
+    query: &str,
+    offset: u64,
+    limit: u64,
+) -> Result<(String, String), ValueError> {
+    let query = query.trim().trim_end_matches(';').trim_end();
+    let words = top_level_words(query);
+    let clause = words
+        .iter()
+        .find(|w| ["ROWS", "OFFSET", "FETCH"].contains(&w.as_str()))
+        .cloned()
+        .or_else(|| {
+            words
+                .windows(2)
+                .find(|w| w == &["FOR", "UPDATE"] || w == &["WITH", "LOCK"])
+                .map(|w| w.join(" "))
+        });
+    if let Some(clause) = clause {
+        return Err(ValueError::new(&format!(
+            "can't page a query with {}",
+            clause
+        )));
+    }
+    Ok((
+        format!("SELECT COUNT(*) FROM ({})", query),
+        format!(
+            "{} OFFSET {} ROWS FETCH NEXT {} ROWS ONLY",
+            query, offset, limit
+        ),
+    ))
+}
+
+// transaction id for op_reconnect, little endian in 4 bytes or 8 for larger ids
+pub fn transaction_id_bytes(id: u64) -> Vec<u8> {
+    if id <= u32::MAX as u64 {
+        (id as u32).to_le_bytes().to_vec()
+    } else {
+        id.to_le_bytes().to_vec()
+    }
+}
+
+// event parameter block of op_que_events, the counts (little endian) seen so far by name
+pub fn event_block(names: &[String], counts: &HashMap<String, u32>) -> Vec<u8> {
+    let mut v: Vec<u8> = vec![1]; // EPB_version1
+    for name in names {
+        v.push(name.len() as u8);
+        v.extend(name.as_bytes());
+        v.extend(counts.get(name).copied().unwrap_or(0).to_le_bytes());
+    }
+    v
+}
+
+// counts by name in the event parameter block of op_event, None if malformed
+pub fn parse_event_block(buf: &[u8]) -> Option<HashMap<String, u32>> {
+    if buf.first() != Some(&1) {
+        return None;
+    }
+    let mut counts = HashMap::new();
+    let mut i = 1;
+    while i < buf.len() {
+        let ln = buf[i] as usize;
+        let count = buf.get(i + 1 + ln..i + 5 + ln)?;
+        counts.insert(
+            bytes_to_lossy_str(&buf[i + 1..i + 1 + ln]),
+            bytes_to_uint32(count),
+        );
+        i += ln + 5;
+    }
+    Some(counts)
+}
+
+// query returning a row when the name in the column of a system table matches,
+// a "quoted" name exactly (with "" for a double quote), otherwise case-insensitively
+pub fn object_exists_query(table: &str, column: &str, name: &str) -> (String, String) {
+    if name.len() >= 2 && name.starts_with('"') && name.ends_with('"') {
+        (
+            format!("SELECT 1 FROM {} WHERE TRIM({}) = ?", table, column),
+            name[1..name.len() - 1].replace("\"\"", "\""),
+        )
+    } else {
+        (
+            format!("SELECT 1 FROM {} WHERE UPPER(TRIM({})) = ?", table, column),
+            name.trim().to_uppercase(),
+        )
+    }
+}
+
+// name as a double quoted identifier for SQL, "quoted" names are kept as is
+// (with "" for a double quote) and other names are uppercased like Firebird does
+pub fn quote_identifier(name: &str) -> String {
+    let name = if name.len() >= 2 && name.starts_with('"') && name.ends_with('"') {
+        name[1..name.len() - 1].replace("\"\"", "\"")
+    } else {
+        name.trim().to_uppercase()
+    };
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+// isc_info_sql_records response: [23, len(2), (item, len(2), count)*, isc_info_end]
+pub fn parse_rowcount(buf: &[u8], stmt_type: u32) -> usize {
+    let mut rowcount: i64 = 0;
+    let mut i: usize = 3;
+    while i + 3 <= buf.len() && buf[i] != ISC_INFO_END {
+        let item = buf[i] as u32;
+        let ln = bytes_to_int16(&buf[i + 1..i + 3]) as usize;
+        if i + 3 + ln > buf.len() {
+            break;
+        }
+        let count = bytes_to_int(&buf[i + 3..i + 3 + ln]);
+        if stmt_type == ISC_INFO_SQL_STMT_SELECT {
+            if item == ISC_INFO_REQ_SELECT_COUNT {
+                rowcount += count;
+            }
+        } else if item == ISC_INFO_REQ_INSERT_COUNT
+            || item == ISC_INFO_REQ_UPDATE_COUNT
+            || item == ISC_INFO_REQ_DELETE_COUNT
+        {
+            rowcount += count;
+        }
+        i += 3 + ln;
+    }
+    rowcount as usize
+}
+
+// info response of [item, len(2), value] clusters cut short by the buffer
+// length, the server puts isc_info_truncated where isc_info_end would be
+pub fn is_info_truncated(buf: &[u8]) -> bool {
+    let mut i: usize = 0;
+    while i + 3 <= buf.len() {
+        match buf[i] {
+            ISC_INFO_END => return false,
+            ISC_INFO_TRUNCATED => return true,
+            _ => i += 3 + bytes_to_uint16(&buf[i + 1..i + 3]) as usize,
+        }
+    }
+    i < buf.len() && buf[i] == ISC_INFO_TRUNCATED
+}
+
+// info response: [item, len(2), unsigned little endian value, ...]
+fn parse_info_uint(buf: &[u8], item: u32) -> Option<u64> {
+    if buf.len() < 3 || buf[0] as u32 != item {
+        return None;
+    }
+    let ln = bytes_to_uint16(&buf[1..3]) as usize;
+    if 3 + ln > buf.len() || ln > 8 {
+        return None;
+    }
+    let mut v: u64 = 0;
+    for (i, c) in buf[3..3 + ln].iter().enumerate() {
+        v += (*c as u64) << (8 * i);
+    }
+    Some(v)
+}
+
+// isc_info_tra_id response: [4, len(2), id, isc_info_end]
+pub fn parse_transaction_id(buf: &[u8]) -> Option<u64> {
+    parse_info_uint(buf, ISC_INFO_TRA_ID)
+}
+
+// isc_info_attachment_id response: [22, len(2), id, isc_info_end]
+pub fn parse_attachment_id(buf: &[u8]) -> Option<u64> {
+    parse_info_uint(buf, ISC_INFO_ATTACHMENT_ID as u32)
+}
+
+// isc_info_blob_total_length response: [6, len(2), length, isc_info_end]
+pub fn parse_blob_total_length(buf: &[u8]) -> Option<u64> {
+    parse_info_uint(buf, ISC_INFO_BLOB_TOTAL_LENGTH)
+}
+
+#[test]
+fn test_convert_date() {
+    assert_eq!(convert_date(1858, 11, 17).unwrap(), bint32_to_bytes(0));
+    assert_eq!(convert_date(1, 1, 1).unwrap(), bint32_to_bytes(-678575));
+    assert_eq!(
+        convert_date(9999, 12, 31).unwrap(),
+        bint32_to_bytes(2973483)
+    );
+
+    for (y, m, d) in [(1, 1, 1), (1858, 11, 17), (1967, 8, 11), (9999, 12, 31)] {
+        assert_eq!(
+            bytes_to_naive_date(&convert_date(y, m, d).unwrap()),
+            chrono::NaiveDate::from_ymd_opt(y, m, d).unwrap()
+        );
+    }
+
+    assert!(convert_date(0, 12, 31).is_err());
+    assert!(convert_date(-1, 1, 1).is_err());
+    assert!(convert_date(10000, 1, 1).is_err());
+}
+
+#[test]
+fn test_convert_time() {
+    // (nanosecond, value stored in 1/10000 second, nanosecond read back)
+    for (nano, fraction, read) in [
+        (0, 0, 0),
+        (100_000_000, 1000, 100_000_000),   // .1
+        (10_000_000, 100, 10_000_000),      // .01
+        (1_000_000, 10, 1_000_000),         // .001
+        (100_000, 1, 100_000),              // .0001
+        (123_456_789, 1234, 123_400_000),   // truncated
+        (999_999_999, 9999, 999_900_000),   // truncated
+        (1_500_000_000, 9999, 999_900_000), // leap second
+    ] {
+        let b = convert_time(23, 59, 58, nano);
+        assert_eq!(b, bint32_to_bytes(86398 * 10000 + fraction));
+        assert_eq!(
+            bytes_to_naive_time(&b),
+            chrono::NaiveTime::from_hms_nano_opt(23, 59, 58, read).unwrap()
+        );
+    }
+    assert_eq!(convert_time(0, 0, 0, 0), [0, 0, 0, 0]);
+
+    let dt = chrono::NaiveDate::from_ymd_opt(2024, 2, 29)
+        .unwrap()
+        .and_hms_milli_opt(12, 34, 56, 789)
+        .unwrap();
+    let mut b = convert_date(2024, 2, 29).unwrap().to_vec();
+    b.extend(convert_time(12, 34, 56, 789_000_000));
+    assert_eq!(bytes_to_naive_date_time(&b), dt);
+}
+
+#[test]
+fn test_parse_rowcount() {
+    let buf = [
+        &[23, 29, 0][..],
+        &[15, 4, 0, 2, 0, 0, 0][..], // update
+        &[16, 4, 0, 3, 0, 0, 0][..], // delete
+        &[13, 4, 0, 5, 0, 0, 0][..], // select
+        &[14, 4, 0, 7, 0, 0, 0][..], // insert
+        &[1][..],
+    ]
+    .concat();
+    assert_eq!(parse_rowcount(&buf, ISC_INFO_SQL_STMT_SELECT), 5);
+    assert_eq!(parse_rowcount(&buf, 2), 12);
+
+    // the 8 bytes object id of op_response is not an info buffer
+    assert_eq!(parse_rowcount(&[0; 8], ISC_INFO_SQL_STMT_SELECT), 0);
+    assert_eq!(parse_rowcount(&[], 2), 0);
+}
+
+#[test]
+fn test_bytes_to_lossy_str() {
+    assert_eq!(bytes_to_lossy_str(b"FOO"), "FOO");
+    // "CAF\xc9" in WIN1252
+    assert_eq!(bytes_to_lossy_str(&[0x43, 0x41, 0x46, 0xc9]), "CAF\u{fffd}");
+}
+
+#[test]
+fn test_parse_transaction_id() {
+    assert_eq!(
+        parse_transaction_id(&[4, 4, 0, 0x10, 0x27, 0, 0x80, 1]),
+        Some(0x8000_2710)
+    );
+    assert_eq!(
+        parse_transaction_id(&[4, 8, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1]),
+        Some(0x1_0000_0001)
+    );
+    assert_eq!(parse_transaction_id(&[2, 0, 0, 1]), None);
+    assert_eq!(parse_transaction_id(&[4, 4, 0, 1]), None);
+}
+
+#[test]
+fn test_parse_attachment_id() {
+    assert_eq!(parse_attachment_id(&[22, 4, 0, 42, 0, 0, 0, 1]), Some(42));
+    assert_eq!(parse_attachment_id(&[4, 4, 0, 42, 0, 0, 0, 1]), None);
+}
+
+#[test]
+fn test_parse_blob_total_length() {
+    assert_eq!(
+        parse_blob_total_length(&[6, 4, 0, 0x00, 0x10, 0, 0, 1]),
+        Some(4096)
+    );
+    assert_eq!(parse_blob_total_length(&[4, 4, 0, 0, 0x10, 0, 0, 1]), None);
+}
+
+#[test]
+fn test_is_info_truncated() {
+    assert!(!is_info_truncated(&[4, 4, 0, 2, 2, 2, 2, 1]));
+    assert!(is_info_truncated(&[4, 4, 0, 2, 2, 2, 2, 2]));
+    assert!(is_info_truncated(&[2]));
+    assert!(!is_info_truncated(&[1]));
+    assert!(!is_info_truncated(&[]));
+}
+
+#[test]
+fn test_ends_transaction() {
+    assert!(ends_transaction("commit"));
+    assert!(ends_transaction(" Commit Work; "));
+    assert!(ends_transaction("ROLLBACK"));
+    assert!(!ends_transaction("COMMIT RETAIN"));
+    assert!(!ends_transaction("ROLLBACK TO SAVEPOINT sp1"));
+    assert!(!ends_transaction("delete from foo"));
+}
+
+#[test]
+fn test_split_sql_script() {
+    assert_eq!(
+        split_sql_script("create table foo (a integer);\ninsert into foo values (1);"),
+        vec!["create table foo (a integer)", "insert into foo values (1)"]
+    );
+    // terminators in literals, quoted identifiers and comments
+    assert_eq!(
+        split_sql_script(
+            "insert into \"a;b\" values ('x;''y'); -- c;d\n/* e; */ select 1 from rdb$database"
+        ),
+        vec![
+            "insert into \"a;b\" values ('x;''y')",
+            "-- c;d\n/* e; */ select 1 from rdb$database"
+        ]
+    );
+    assert_eq!(
+        split_sql_script(
+            "SET TERM ^ ;\n\
+             CREATE TRIGGER t FOR foo BEFORE INSERT AS\n\
+             BEGIN\n  NEW.a = 1;\nEND^\n\
+             set term ;^\n\
+             commit;"
+        ),
+        vec![
+            "CREATE TRIGGER t FOR foo BEFORE INSERT AS\nBEGIN\n  NEW.a = 1;\nEND",
+            "commit"
+        ]
+    );
+    assert!(split_sql_script(" ;\n-- only a comment\n").is_empty());
+    // isql settings are skipped, server side SET statements are kept
+    assert_eq!(
+        split_sql_script(
+            "SET SQL DIALECT 3;\nset names UTF8;\nSET AUTODDL ON;\n\
+             SET TRANSACTION READ COMMITTED;\nSET GENERATOR g TO 1;"
+        ),
+        vec!["SET TRANSACTION READ COMMITTED", "SET GENERATOR g TO 1"]
+    );
+}
+
+#[test]
+fn test_transaction_id_bytes() {
+    assert_eq!(transaction_id_bytes(1), vec![1, 0, 0, 0]);
+    assert_eq!(transaction_id_bytes(0x01020304), vec![4, 3, 2, 1]);
+    assert_eq!(
+        transaction_id_bytes(0x1_0000_0000),
+        vec![0, 0, 0, 0, 1, 0, 0, 0]
+    );
+}
+
+#[test]
+fn test_event_block() {
+    let names = vec!["a".to_string(), "bc".to_string()];
+    let mut counts = HashMap::new();
+    counts.insert("bc".to_string(), 0x0102);
+    let block = event_block(&names, &counts);
+    assert_eq!(
+        block,
+        vec![1, 1, b'a', 0, 0, 0, 0, 2, b'b', b'c', 2, 1, 0, 0]
+    );
+    let parsed = parse_event_block(&block).unwrap();
+    assert_eq!(parsed.len(), 2);
+    assert_eq!(parsed["a"], 0);
+    assert_eq!(parsed["bc"], 0x0102);
+
+    assert_eq!(parse_event_block(&[]), None);
+    assert_eq!(parse_event_block(&[2]), None);
+    assert_eq!(parse_event_block(&[1, 1, b'a', 0, 0]), None);
+}
+
+#[test]
+fn test_object_exists_query() {
+    assert_eq!(
+        object_exists_query("RDB$RELATIONS", "RDB$RELATION_NAME", "foo"),
+        (
+            "SELECT 1 FROM RDB$RELATIONS WHERE UPPER(TRIM(RDB$RELATION_NAME)) = ?".to_string(),
+            "FOO".to_string()
+        )
+    );
+    assert_eq!(
+        object_exists_query("RDB$RELATIONS", "RDB$RELATION_NAME", "\"my \"\"foo\"\"\""),
+        (
+            "SELECT 1 FROM RDB$RELATIONS WHERE TRIM(RDB$RELATION_NAME) = ?".to_string(),
+            "my \"foo\"".to_string()
+        )
+    );
+}
+
+#[test]
+fn test_quote_identifier() {
+    assert_eq!(quote_identifier("gen_foo"), "\"GEN_FOO\"");
+    assert_eq!(quote_identifier("\"MixedCase\""), "\"MixedCase\"");
+    assert_eq!(quote_identifier("\"my \"\"foo\"\"\""), "\"my \"\"foo\"\"\"");
+    assert_eq!(
+        quote_identifier("a, 0) FROM RDB$DATABASE; --"),
+        "\"A, 0) FROM RDB$DATABASE; --\""
+    );
+    assert_eq!(quote_identifier("x\"y"), "\"X\"\"Y\"");
+}
+
+#[test]
+fn test_scaled_to_string() {
+    assert_eq!(scaled_to_string(12345, -2), "123.45");
+    assert_eq!(scaled_to_string(-5, -3), "-0.005");
+    assert_eq!(scaled_to_string(12, 2), "1200");
+    assert_eq!(scaled_to_string(0, -1), "0.0");
+    assert_eq!(
+        scaled_to_string(i128::MIN, -38),
+        "-1.70141183460469231731687303715884105728"
+    );
+}
+
+#[test]
+fn test_count_and_page_queries() {
+    assert_eq!(
+        count_and_page_queries("select a from foo where b = ? order by a;\n", 20, 10).unwrap(),
+        (
+            "SELECT COUNT(*) FROM (select a from foo where b = ? order by a)".to_string(),
+            "select a from foo where b = ? order by a OFFSET 20 ROWS FETCH NEXT 10 ROWS ONLY"
+                .to_string()
+        )
+    );
+    // in subqueries, literals and quoted identifiers they don't count
+    assert!(count_and_page_queries(
+        "select a, 'rows' from foo where a in (select b from bar rows 1) and \"FETCH\" = 1",
+        0,
+        10
+    )
+    .is_ok());
+
+    assert!(count_and_page_queries("select a from foo rows 5", 0, 10).is_err());
+    assert!(count_and_page_queries("select a from foo offset 5 rows", 0, 10).is_err());
+    assert!(count_and_page_queries("select a from foo fetch first 5 rows only", 0, 10).is_err());
+    assert!(count_and_page_queries("select a from foo for update", 0, 10).is_err());
+    assert!(count_and_page_queries("select a from foo with lock", 0, 10).is_err());
+}
@@ -25,25 +25,198 @@ use super::error::Error;
 use crypto::digest::Digest;
 use crypto::sha2::Sha256;
 use hex;
+use socket2::{Domain, Protocol, Socket, Type};
+use std::io;
 use std::io::prelude::*;
-use std::net::TcpStream;
+use std::net::{IpAddr, SocketAddr, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+// bind_address is "ip" or "ip:port"
+fn parse_bind_address(bind_address: &str) -> io::Result<SocketAddr> {
+    if let Ok(addr) = bind_address.parse::<SocketAddr>() {
+        return Ok(addr);
+    }
+    match bind_address.parse::<IpAddr>() {
+        Ok(ip) => Ok(SocketAddr::new(ip, 0)),
+        Err(_) => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid bind_address: {}", bind_address),
+        )),
+    }
+}
+
+// connect from the local address bind_address
+pub(crate) fn connect_from(host: &str, port: u16, bind_address: &str) -> io::Result<TcpStream> {
+    let local = parse_bind_address(bind_address)?;
+    let mut last_err = io::Error::new(
+        io::ErrorKind::AddrNotAvailable,
+        format!(
+            "no {} address for {}",
+            if local.is_ipv4() { "IPv4" } else { "IPv6" },
+            host
+        ),
+    );
+    for addr in (host, port).to_socket_addrs()? {
+        if addr.is_ipv4() != local.is_ipv4() {
+            continue;
+        }
+        let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+        socket.bind(&local.into())?;
+        match socket.connect(&addr.into()) {
+            Ok(()) => return Ok(socket.into()),
+            Err(e) => last_err = e,
+        }
+    }
+    Err(last_err)
+}
+
+// the address being connected to, for multi host setups
+pub(crate) fn connect_error(host: &str, port: u16, e: io::Error) -> io::Error {
+    io::Error::new(
+        e.kind(),
+        format!("can't connect to {}:{}: {}", host, port, e),
+    )
+}
+
+enum Stream {
+    Tcp(TcpStream),
+    #[cfg(feature = "tls")]
+    Tls(native_tls::TlsStream<TcpStream>),
+}
+
+impl Stream {
+    #[cfg(feature = "tls")]
+    fn tls(host: &str, stream: TcpStream) -> Result<Stream, Error> {
+        let connector =
+            native_tls::TlsConnector::new().map_err(|e| io::Error::other(e.to_string()))?;
+        let stream = connector
+            .connect(host, stream)
+            .map_err(|e| io::Error::other(e.to_string()))?;
+        Ok(Stream::Tls(stream))
+    }
+
+    #[cfg(not(feature = "tls"))]
+    fn tls(_host: &str, _stream: TcpStream) -> Result<Stream, Error> {
+        Err(Error::IoError(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "tls connection needs the tls feature of firebirust",
+        )))
+    }
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Stream::Tcp(s) => s.read(buf),
+            #[cfg(feature = "tls")]
+            Stream::Tls(s) => s.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Stream::Tcp(s) => s.write(buf),
+            #[cfg(feature = "tls")]
+            Stream::Tls(s) => s.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Stream::Tcp(s) => s.flush(),
+            #[cfg(feature = "tls")]
+            Stream::Tls(s) => s.flush(),
+        }
+    }
+}
+
+// byte stream under the wire protocol, the server socket or canned bytes in tests
+pub(crate) trait Transport: Read + Write {
+    // None blocks reads until data arrives
+    fn set_read_timeout(&self, _timeout: Option<Duration>) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Transport for Stream {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        match self {
+            Stream::Tcp(s) => s.set_read_timeout(timeout),
+            #[cfg(feature = "tls")]
+            Stream::Tls(s) => s.get_ref().set_read_timeout(timeout),
+        }
+    }
+}
+
+// replays server responses from a buffer and discards the requests
+#[cfg(test)]
+pub(crate) struct MemoryTransport {
+    input: io::Cursor<Vec<u8>>,
+}
+
+#[cfg(test)]
+impl MemoryTransport {
+    pub(crate) fn new(input: &[u8]) -> MemoryTransport {
+        MemoryTransport {
+            input: io::Cursor::new(input.to_vec()),
+        }
+    }
+}
+
+#[cfg(test)]
+impl Read for MemoryTransport {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.input.read(buf)
+    }
+}
+
+#[cfg(test)]
+impl Transport for MemoryTransport {}
+
+#[cfg(test)]
+impl Write for MemoryTransport {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
 
 pub struct WireChannel {
-    stream: TcpStream,
+    stream: Box<dyn Transport>,
     read_buf: Vec<u8>,
     read_trans: Option<Box<dyn CryptTranslator>>,
     write_trans: Option<Box<dyn CryptTranslator>>,
 }
 
 impl WireChannel {
-    pub fn new(host: &str, port: u16) -> Result<WireChannel, Error> {
-        let stream = TcpStream::connect(format!("{}:{}", host, port))?;
-        Ok(WireChannel {
+    pub fn new(host: &str, port: u16, tls: bool, bind_address: &str) -> Result<WireChannel, Error> {
+        let stream = if bind_address.is_empty() {
+            TcpStream::connect(format!("{}:{}", host, port))
+        } else {
+            connect_from(host, port, bind_address)
+        }
+        .map_err(|e| connect_error(host, port, e))?;
+        // TLS is set up before the Firebird handshake
+        let stream = if tls {
+            Stream::tls(host, stream)?
+        } else {
+            Stream::Tcp(stream)
+        };
+        Ok(WireChannel::from_transport(Box::new(stream)))
+    }
+
+    pub(crate) fn from_transport(stream: Box<dyn Transport>) -> WireChannel {
+        WireChannel {
             stream,
             read_buf: Vec::new(),
             read_trans: None,
             write_trans: None,
-        })
+        }
     }
 
     pub fn set_crypt_key(&mut self, plugin: &[u8], key: &[u8], nonce: &[u8]) {
@@ -64,6 +237,10 @@ impl WireChannel {
 
         while self.read_buf.len() < n {
             let ln = self.stream.read(&mut input_buf)?;
+            if ln == 0 {
+                // closed by the peer, waiting would never end
+                return Err(Error::IoError(io::ErrorKind::UnexpectedEof.into()));
+            }
             self.read_buf.extend(input_buf[..ln].iter().copied());
         }
 
@@ -80,6 +257,35 @@ impl WireChannel {
         }
     }
 
+    // whether data arrives within timeout, without consuming it
+    pub fn wait_readable(&mut self, timeout: Duration) -> Result<bool, Error> {
+        if !self.read_buf.is_empty() {
+            return Ok(true);
+        }
+        let mut input_buf = [0u8; 4096];
+        // a zero timeout is rejected by the socket
+        self.stream
+            .set_read_timeout(Some(timeout.max(Duration::from_millis(1))))?;
+        let result = self.stream.read(&mut input_buf);
+        self.stream.set_read_timeout(None)?;
+        match result {
+            Ok(0) => Err(Error::IoError(io::ErrorKind::UnexpectedEof.into())),
+            Ok(ln) => {
+                self.read_buf.extend(input_buf[..ln].iter().copied());
+                Ok(true)
+            }
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                Ok(false)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
     pub fn write(&mut self, buf: &[u8]) -> Result<(), Error> {
         if let Some(ref mut trans) = self.write_trans {
             self.stream.write(&*trans.translate(buf))?;
@@ -89,3 +295,55 @@ impl WireChannel {
         Ok(())
     }
 }
+
+#[test]
+fn test_parse_bind_address() {
+    assert_eq!(
+        parse_bind_address("192.168.0.10").unwrap(),
+        "192.168.0.10:0".parse::<SocketAddr>().unwrap()
+    );
+    assert_eq!(
+        parse_bind_address("192.168.0.10:40000").unwrap(),
+        "192.168.0.10:40000".parse::<SocketAddr>().unwrap()
+    );
+    assert!(parse_bind_address("::1").unwrap().is_ipv6());
+    assert!(parse_bind_address("localhost").is_err());
+}
+
+#[test]
+fn test_connect_error() {
+    let e = connect_error(
+        "db1.example.com",
+        3050,
+        io::Error::new(io::ErrorKind::ConnectionRefused, "connection refused"),
+    );
+    assert_eq!(e.kind(), io::ErrorKind::ConnectionRefused);
+    assert_eq!(
+        e.to_string(),
+        "can't connect to db1.example.com:3050: connection refused"
+    );
+}
+
+#[test]
+fn test_memory_transport() {
+    let mut channel = WireChannel::from_transport(Box::new(MemoryTransport::new(&[1, 2, 3, 4, 5])));
+    channel.write(&[9, 9]).unwrap();
+    assert_eq!(channel.read(2).unwrap(), vec![1, 2]);
+    assert_eq!(channel.read(3).unwrap(), vec![3, 4, 5]);
+    match channel.read(1) {
+        Err(Error::IoError(e)) => assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof),
+        _ => panic!("expected UnexpectedEof"),
+    }
+}
+
+#[test]
+fn test_wait_readable() {
+    let mut channel = WireChannel::from_transport(Box::new(MemoryTransport::new(&[1, 2, 3])));
+    assert!(channel.wait_readable(Duration::from_millis(1)).unwrap());
+    // the data is kept for read()
+    assert_eq!(channel.read(2).unwrap(), vec![1, 2]);
+    assert!(channel.wait_readable(Duration::ZERO).unwrap());
+    assert_eq!(channel.read(1).unwrap(), vec![3]);
+    // closed by the peer
+    assert!(channel.wait_readable(Duration::from_millis(1)).is_err());
+}
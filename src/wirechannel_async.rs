@@ -22,22 +22,116 @@
 
 use super::crypt_translater::{Arc4, ChaCha, CryptTranslator};
 use super::error::Error;
+use super::wirechannel::{connect_error, connect_from};
+use async_std::future;
+use async_std::io;
 use async_std::io::prelude::*;
 use async_std::net::TcpStream;
+use async_std::task;
 use crypto::digest::Digest;
 use crypto::sha2::Sha256;
 use hex;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+enum Stream {
+    Tcp(TcpStream),
+    #[cfg(feature = "tls")]
+    Tls(async_native_tls::TlsStream<TcpStream>),
+}
+
+impl Stream {
+    #[cfg(feature = "tls")]
+    async fn tls(host: &str, stream: TcpStream) -> Result<Stream, Error> {
+        let stream = async_native_tls::connect(host, stream)
+            .await
+            .map_err(|e| io::Error::other(e.to_string()))?;
+        Ok(Stream::Tls(stream))
+    }
+
+    #[cfg(not(feature = "tls"))]
+    async fn tls(_host: &str, _stream: TcpStream) -> Result<Stream, Error> {
+        Err(Error::IoError(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "tls connection needs the tls feature of firebirust",
+        )))
+    }
+}
+
+impl io::Read for Stream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Stream::Tcp(s) => Pin::new(s).poll_read(cx, buf),
+            #[cfg(feature = "tls")]
+            Stream::Tls(s) => Pin::new(s).poll_read(cx, buf),
+        }
+    }
+}
+
+impl io::Write for Stream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Stream::Tcp(s) => Pin::new(s).poll_write(cx, buf),
+            #[cfg(feature = "tls")]
+            Stream::Tls(s) => Pin::new(s).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Stream::Tcp(s) => Pin::new(s).poll_flush(cx),
+            #[cfg(feature = "tls")]
+            Stream::Tls(s) => Pin::new(s).poll_flush(cx),
+        }
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Stream::Tcp(s) => Pin::new(s).poll_close(cx),
+            #[cfg(feature = "tls")]
+            Stream::Tls(s) => Pin::new(s).poll_close(cx),
+        }
+    }
+}
 
 pub struct WireChannelAsync {
-    stream: TcpStream,
+    stream: Stream,
     read_buf: Vec<u8>,
     read_trans: Option<Box<dyn CryptTranslator>>,
     write_trans: Option<Box<dyn CryptTranslator>>,
 }
 
 impl WireChannelAsync {
-    pub async fn new(host: &str, port: u16) -> Result<WireChannelAsync, Error> {
-        let stream = TcpStream::connect(format!("{}:{}", host, port)).await?;
+    pub async fn new(
+        host: &str,
+        port: u16,
+        tls: bool,
+        bind_address: &str,
+    ) -> Result<WireChannelAsync, Error> {
+        let stream = if bind_address.is_empty() {
+            TcpStream::connect(format!("{}:{}", host, port)).await
+        } else {
+            let (host_name, bind_address) = (host.to_string(), bind_address.to_string());
+            task::spawn_blocking(move || connect_from(&host_name, port, &bind_address))
+                .await
+                .map(TcpStream::from)
+        }
+        .map_err(|e| connect_error(host, port, e))?;
+        // TLS is set up before the Firebird handshake
+        let stream = if tls {
+            Stream::tls(host, stream).await?
+        } else {
+            Stream::Tcp(stream)
+        };
         Ok(WireChannelAsync {
             stream,
             read_buf: Vec::new(),
@@ -64,6 +158,10 @@ impl WireChannelAsync {
 
         while self.read_buf.len() < n {
             let ln = self.stream.read(&mut input_buf).await?;
+            if ln == 0 {
+                // closed by the peer, waiting would never end
+                return Err(Error::IoError(io::ErrorKind::UnexpectedEof.into()));
+            }
             self.read_buf.extend(input_buf[..ln].iter().copied());
         }
 
@@ -80,6 +178,24 @@ impl WireChannelAsync {
         }
     }
 
+    // whether data arrives within timeout, without consuming it
+    pub async fn wait_readable(&mut self, timeout: Duration) -> Result<bool, Error> {
+        if !self.read_buf.is_empty() {
+            return Ok(true);
+        }
+        let mut input_buf = [0u8; 4096];
+        // nothing is read when the timeout cancels the read
+        match future::timeout(timeout, self.stream.read(&mut input_buf)).await {
+            Err(_) => Ok(false),
+            Ok(Ok(0)) => Err(Error::IoError(io::ErrorKind::UnexpectedEof.into())),
+            Ok(Ok(ln)) => {
+                self.read_buf.extend(input_buf[..ln].iter().copied());
+                Ok(true)
+            }
+            Ok(Err(e)) => Err(e.into()),
+        }
+    }
+
     pub async fn write(&mut self, buf: &[u8]) -> Result<(), Error> {
         if let Some(ref mut trans) = self.write_trans {
             self.stream.write(&*trans.translate(buf)).await?;
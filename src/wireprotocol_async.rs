@@ -23,14 +23,13 @@
 #![allow(dead_code)]
 
 use async_std::io::prelude::*;
-use async_std::task;
 use hex;
 use num_bigint::BigInt;
 use std::collections::{HashMap, HashSet};
 
-use super::cellvalue::CellValue;
+use super::cellvalue::{BlobId, CellValue};
 use super::conn_params::ConnParams;
-use super::error::{Error, FirebirdError};
+use super::error::{Error, FirebirdError, ParamError, UrlError, ValueError};
 use super::wirechannel_async::WireChannelAsync;
 use super::xsqlvar::XSQLVar;
 use super::*;
@@ -39,6 +38,11 @@ const PLUGIN_NAME_LIST: &str = "Srp256,Srp";
 const BUFFER_LEN: u32 = 1024;
 const MAX_CHAR_LENGTH: usize = 32767;
 const BLOB_SEGMENT_SIZE: usize = 32000;
+// the message BLR counts 2 items (value and null indicator) per parameter in 16 bits
+const MAX_PARAMS: usize = 0xFFFF / 2;
+
+// (rows, status, error which stopped decoding the rows)
+pub type FetchResponse = (Vec<Vec<CellValue>>, u32, Option<Error>);
 
 macro_rules! debug_print {
     //    ($( $args:expr ),*) => { println!( $( $args ),* ); }
@@ -65,10 +69,14 @@ fn info_sql_select_describe_vars() -> [u8; 13] {
 
 pub struct WireProtocolAsync {
     write_buf: Vec<u8>,
+    deferred_buf: Vec<u8>, // requests of dropped values, sent ahead of the next one
+    deferred_count: i32,
 
     channel: WireChannelAsync,
     host: String,
     port: u16,
+    tls: bool,
+    bind_address: String, // also for the auxiliary connection of events
 
     pub(crate) db_handle: i32,
 
@@ -76,12 +84,24 @@ pub struct WireProtocolAsync {
     accept_architecture: i32,
     pub(crate) accept_type: i32,
     pub(crate) lazy_response_count: i32,
+    pub(crate) warnings: Vec<String>, // ISC_ARG_WARNING messages not taken yet
+    pub(crate) closed: bool,          // detached by ConnectionAsync::close()
 
     accept_plugin_name: String,
     auth_data: Option<Vec<u8>>,
 
     // Time Zone
     timezone: String,
+
+    // transactions are started with isc_tpb_read instead of isc_tpb_write
+    read_only: bool,
+
+    process_name: String,
+    no_linger: bool, // isc_dpb_nolinger: no database linger after detach
+    reset_icu: bool, // isc_dpb_reset_icu: after an ICU upgrade on the server
+
+    // reply buffer length of info requests and prepare
+    pub(crate) info_buffer_len: u32,
 }
 
 impl WireProtocolAsync {
@@ -91,17 +111,36 @@ impl WireProtocolAsync {
     ) -> Result<WireProtocolAsync, Error> {
         Ok(WireProtocolAsync {
             write_buf: Vec::new(),
-            channel: WireChannelAsync::new(&params.host, params.port).await?,
+            deferred_buf: Vec::new(),
+            deferred_count: 0,
+            channel: WireChannelAsync::new(
+                &params.host,
+                params.port,
+                option_params["tls"] == "true",
+                &option_params["bind_address"],
+            )
+            .await?,
             host: params.host.to_string(),
             port: params.port,
+            tls: option_params["tls"] == "true",
+            bind_address: option_params["bind_address"].to_string(),
             db_handle: -1,
             protocol_version: -1,
             accept_architecture: -1,
             accept_type: -1,
             lazy_response_count: 0,
+            warnings: Vec::new(),
+            closed: false,
             accept_plugin_name: "".to_string(),
             auth_data: None,
             timezone: option_params["timezone"].to_string(),
+            read_only: option_params["read_only"] == "true",
+            process_name: option_params["process_name"].to_string(),
+            no_linger: option_params["no_linger"] == "true",
+            reset_icu: option_params["reset_icu"] == "true",
+            info_buffer_len: option_params["info_buffer_len"]
+                .parse()
+                .unwrap_or(BUFFER_LEN),
         })
     }
 
@@ -162,11 +201,32 @@ impl WireProtocolAsync {
     }
 
     async fn send_packets(&mut self) -> Result<(), Error> {
+        if self.deferred_count > 0 {
+            // their responses come first and are skipped like lazy ones
+            let mut buf = std::mem::take(&mut self.deferred_buf);
+            buf.append(&mut self.write_buf);
+            self.write_buf = buf;
+            self.lazy_response_count += self.deferred_count;
+            self.deferred_count = 0;
+        }
         self.channel.write(&self.write_buf).await?;
         self.write_buf.clear();
         Ok(())
     }
 
+    // a request from Drop, which can't await send_packets()
+    pub(crate) fn defer_request(&mut self, op: u32, handle: i32, args: &[u32]) {
+        if self.closed {
+            return;
+        }
+        self.deferred_buf.extend(op.to_be_bytes());
+        self.deferred_buf.extend((handle as u32).to_be_bytes());
+        for n in args {
+            self.deferred_buf.extend(n.to_be_bytes());
+        }
+        self.deferred_count += 1;
+    }
+
     fn suspend_buffer(&mut self) -> Vec<u8> {
         let mut v: Vec<u8> = Vec::new();
         v.append(&mut self.write_buf);
@@ -182,6 +242,10 @@ impl WireProtocolAsync {
     }
 
     async fn recv_packets_alignment(&mut self, n: usize) -> Result<Vec<u8>, Error> {
+        // empty buffers have no padding either, nothing to read
+        if n == 0 {
+            return Ok(Vec::new());
+        }
         let mut padding = n % 4;
         if padding > 0 {
             padding = 4 - padding;
@@ -199,6 +263,8 @@ impl WireProtocolAsync {
         let mut gds_codes: HashSet<u32> = HashSet::new();
         let mut num_arg = 0;
         let mut message = String::new();
+        let mut warning = String::new();
+        let mut in_warning = false;
 
         let mut n = utils::bytes_to_buint32(&self.recv_packets(4).await?);
         while n != ISC_ARG_END {
@@ -207,32 +273,58 @@ impl WireProtocolAsync {
                     gds_code = utils::bytes_to_buint32(&self.recv_packets(4).await?);
                     if gds_code != 0 {
                         gds_codes.insert(gds_code);
-                        message.push_str(errmsgs::error_message_by_id(gds_code));
+                        message.push_str(&errmsgs::error_message_by_id(gds_code));
                         num_arg = 0;
                     }
                 }
                 ISC_ARG_NUMBER => {
                     let num = utils::bytes_to_buint32(&self.recv_packets(4).await?);
-                    if gds_code == 335544436 {
+                    if !in_warning && gds_code == 335544436 {
                         sql_code = num as i32;
                     }
                     num_arg += 1;
                     let place_folder = format!("@{}", num_arg);
-                    message = message.replace(&place_folder, &num.to_string());
+                    let target = if in_warning {
+                        &mut warning
+                    } else {
+                        &mut message
+                    };
+                    *target = target.replace(&place_folder, &(num as i32).to_string());
                 }
                 ISC_ARG_STRING => {
                     let nbytes = utils::bytes_to_buint32(&self.recv_packets(4).await?);
-                    let s =
-                        utils::bytes_to_str(&self.recv_packets_alignment(nbytes as usize).await?);
+                    let s = utils::bytes_to_lossy_str(
+                        &self.recv_packets_alignment(nbytes as usize).await?,
+                    );
                     num_arg += 1;
                     let place_folder = format!("@{}", num_arg);
-                    message = message.replace(&place_folder, &s);
+                    let target = if in_warning {
+                        &mut warning
+                    } else {
+                        &mut message
+                    };
+                    *target = target.replace(&place_folder, &s);
                 }
                 ISC_ARG_INTERPRETED => {
                     let nbytes = utils::bytes_to_buint32(&self.recv_packets(4).await?);
-                    let s =
-                        utils::bytes_to_str(&self.recv_packets_alignment(nbytes as usize).await?);
-                    message.push_str(&s);
+                    let s = utils::bytes_to_lossy_str(
+                        &self.recv_packets_alignment(nbytes as usize).await?,
+                    );
+                    let target = if in_warning {
+                        &mut warning
+                    } else {
+                        &mut message
+                    };
+                    target.push_str(&s);
+                }
+                ISC_ARG_WARNING => {
+                    let code = utils::bytes_to_buint32(&self.recv_packets(4).await?);
+                    if in_warning {
+                        self.warnings.push(warning.trim_end().to_string());
+                    }
+                    in_warning = true;
+                    warning = errmsgs::error_message_by_id(code);
+                    num_arg = 0;
                 }
                 ISC_ARG_SQL_STATE => {
                     let nbytes = utils::bytes_to_buint32(&self.recv_packets(4).await?);
@@ -244,9 +336,14 @@ impl WireProtocolAsync {
             n = utils::bytes_to_buint32(&self.recv_packets(4).await?);
         }
 
+        if in_warning {
+            self.warnings.push(warning.trim_end().to_string());
+        }
+
         Ok((gds_codes, sql_code, message))
     }
 
+    // (object handle, object id (8 bytes), data buffer)
     pub(crate) async fn parse_op_response(&mut self) -> Result<(i32, Vec<u8>, Vec<u8>), Error> {
         let h: i32 = utils::bytes_to_buint32(&self.recv_packets(4).await?) as i32;
         let oid: Vec<u8> = self.recv_packets(8).await?;
@@ -256,7 +353,11 @@ impl WireProtocolAsync {
         let (gds_codes, sql_code, message) = self.parse_status_vector().await?;
 
         if gds_codes.len() > 0 || sql_code != 0 {
-            Err(Error::FirebirdError(FirebirdError::new(&message, sql_code)))
+            let mut gds_codes: Vec<u32> = gds_codes.into_iter().collect();
+            gds_codes.sort();
+            Err(Error::FirebirdError(FirebirdError::with_gds_codes(
+                &message, sql_code, gds_codes,
+            )))
         } else {
             Ok((h, oid, buf))
         }
@@ -278,18 +379,28 @@ impl WireProtocolAsync {
         if opcode == OP_REJECT {
             return Err(Error::FirebirdError(FirebirdError::new("op_reject", 0)));
         }
-        if opcode == OP_RESPONSE {
-            self.parse_op_response().await?; // error
-            panic!("connection error"); // not reach
+        if opcode != OP_COND_ACCEPT && opcode != OP_ACCEPT_DATA {
+            if opcode == OP_RESPONSE {
+                self.parse_op_response().await?; // error
+            }
+            return Err(wireprotocol::protocol_error(format!(
+                "unexpected opcode {} while connecting",
+                opcode
+            )));
         }
 
         self.recv_packets(3).await?;
         self.protocol_version = self.recv_packets(1).await?[0] as i32;
         self.accept_architecture = utils::bytes_to_buint32(&self.recv_packets(4).await?) as i32;
+        // anything else would be decoded into garbled numbers
+        if self.accept_architecture != ARCH_GENERIC {
+            return Err(wireprotocol::protocol_error(format!(
+                "unsupported architecture {}",
+                self.accept_architecture
+            )));
+        }
         self.accept_type = utils::bytes_to_buint32(&self.recv_packets(4).await?) as i32;
 
-        assert!(opcode == OP_COND_ACCEPT || opcode == OP_ACCEPT_DATA);
-
         let mut ln: usize = utils::bytes_to_buint32(&self.recv_packets(4).await?) as usize;
         let mut data = self.recv_packets_alignment(ln).await?;
 
@@ -297,22 +408,38 @@ impl WireProtocolAsync {
         self.accept_plugin_name =
             String::from_utf8_lossy(&self.recv_packets_alignment(ln).await?).to_string();
 
-        // is_authenticated == 0
-        assert_eq!(utils::bytes_to_buint32(&self.recv_packets(4).await?), 0);
+        let is_authenticated = utils::bytes_to_buint32(&self.recv_packets(4).await?);
+        if is_authenticated != 0 {
+            return Err(wireprotocol::protocol_error(format!(
+                "unexpected is_authenticated {} while connecting",
+                is_authenticated
+            )));
+        }
 
         // skip keys
         ln = utils::bytes_to_buint32(&self.recv_packets(4).await?) as usize;
         self.recv_packets_alignment(ln).await?;
 
-        assert!(&self.accept_plugin_name == "Srp" || &self.accept_plugin_name == "Srp256");
+        if &self.accept_plugin_name != "Srp" && &self.accept_plugin_name != "Srp256" {
+            return Err(wireprotocol::protocol_error(format!(
+                "unsupported auth plugin {}",
+                self.accept_plugin_name
+            )));
+        }
 
         if data.len() == 0 {
             self.op_cont_auth(&utils::big_int_to_bytes(client_public))
                 .await?;
-            assert_eq!(
-                utils::bytes_to_buint32(&self.recv_packets(4).await?),
-                OP_CONT_AUTH
-            );
+            let opcode = utils::bytes_to_buint32(&self.recv_packets(4).await?);
+            if opcode != OP_CONT_AUTH {
+                if opcode == OP_RESPONSE {
+                    self.parse_op_response().await?; // error
+                }
+                return Err(wireprotocol::protocol_error(format!(
+                    "unexpected opcode {} waiting for op_cont_auth",
+                    opcode
+                )));
+            }
 
             ln = utils::bytes_to_buint32(&self.recv_packets_alignment(4).await?) as usize;
             data = self.recv_packets_alignment(ln).await?;
@@ -332,7 +459,7 @@ impl WireProtocolAsync {
         let server_public =
             utils::big_int_from_hex_string(&utils::bytes_to_str(&data[4 + ln..]).as_bytes());
         let (auth_data, session_key) = srp::get_client_proof(
-            &username.to_uppercase(),
+            &srp::normalize_user(username),
             password,
             servre_salt,
             client_public,
@@ -434,7 +561,12 @@ impl WireProtocolAsync {
                 }
                 ISC_INFO_TRUNCATED => return Ok(index as isize),
                 ISC_INFO_SQL_DESCRIBE_END => { /* NOTHING */ }
-                _ => panic!("protocol sequence fail!"),
+                _ => {
+                    return Err(wireprotocol::protocol_error(format!(
+                        "unexpected describe item {}",
+                        item
+                    )))
+                }
             }
 
             item = buf[i]
@@ -467,20 +599,34 @@ impl WireProtocolAsync {
                 for _ in 0..col_len {
                     xsqlda.push(XSQLVar::new());
                 }
-                let mut next_index: i16 =
-                    self.parse_select_items(&buf[i + ln..], &mut xsqlda)? as i16;
-                while next_index > 0 {
-                    // more describe vars
+                let mut next_index = self.parse_select_items(&buf[i + ln..], &mut xsqlda)?;
+                // the rest is described from the column cut by isc_info_truncated,
+                // with a larger buffer each time for wide select lists
+                let mut buffer_len = self.info_buffer_len;
+                while next_index >= 0 {
+                    // truncated before the first column of the reply
+                    let start = next_index.max(1);
+                    buffer_len = buffer_len.saturating_mul(2).min(MAX_INFO_BUFFER_LEN);
                     let mut vars: Vec<u8> = Vec::new();
                     vars.push(ISC_INFO_SQL_SQLDA_START);
                     vars.push(2);
-                    vars.write(&utils::int16_to_bytes(next_index as u16))
+                    vars.extend(utils::int16_to_bytes(start as u16));
+                    vars.extend(info_sql_select_describe_vars());
+                    self.op_info(OP_INFO_SQL, stmt_handle, &vars, buffer_len)
                         .await?;
-                    vars.write(&info_sql_select_describe_vars()).await?;
-                    self.op_info_sql(stmt_handle, &vars).await?;
                     let (_, _, buf) = self.op_response().await?;
-                    let ln: usize = utils::bytes_to_uint16(&buf[0..4]) as usize;
-                    next_index = self.parse_select_items(&buf[4 + ln..], &mut xsqlda)? as i16;
+                    // isc_info_sql_select, isc_info_sql_describe_vars, length
+                    let ln: usize = utils::bytes_to_uint16(&buf[2..4]) as usize;
+                    next_index = self.parse_select_items(&buf[4 + ln..], &mut xsqlda)?;
+                    if next_index >= 0 && next_index <= start {
+                        if buffer_len == MAX_INFO_BUFFER_LEN {
+                            return Err(Error::ValueError(ValueError::new(&format!(
+                                "column {} doesn't fit in the describe buffer",
+                                start
+                            ))));
+                        }
+                        next_index = start;
+                    }
                 }
             } else {
                 break;
@@ -491,55 +637,110 @@ impl WireProtocolAsync {
     }
 
     pub async fn rowcount(&mut self, stmt_handle: i32, stmt_type: u32) -> Result<usize, Error> {
-        self.op_info_sql(stmt_handle, &[ISC_INFO_SQL_RECORDS])
+        let buf = self
+            .info_request(OP_INFO_SQL, stmt_handle, &[ISC_INFO_SQL_RECORDS])
             .await?;
-        let (_, buf, _) = self.op_response().await?;
-        let rowcount = if buf.len() >= 32 {
-            if stmt_type == ISC_INFO_SQL_STMT_SELECT {
-                utils::bytes_to_int32(&buf[20..24]) as usize
-            } else {
-                (utils::bytes_to_int32(&buf[27..31])
-                    + utils::bytes_to_int32(&buf[6..10])
-                    + utils::bytes_to_int32(&buf[13..17])) as usize
-            }
-        } else {
-            0
-        };
-        Ok(rowcount)
+        Ok(utils::parse_rowcount(&buf, stmt_type))
     }
 
     pub async fn get_blob_segments(
         &mut self,
-        blob_id: &Vec<u8>,
+        blob_id: &BlobId,
         trans_handle: i32,
     ) -> Result<Vec<u8>, Error> {
         let buf = self.suspend_buffer();
 
+        self.op_open_blob(blob_id, trans_handle).await?;
+        let (blob_handle, _, _) = self.op_response().await?;
+        let blob = self.read_blob_segments(blob_handle).await?;
+        self.close_blob(blob_handle).await?;
+
+        self.resume_buffer(&buf).await;
+        Ok(blob)
+    }
+
+    // None without reading the content when the blob is longer than max_length
+    pub async fn get_blob_segments_limited(
+        &mut self,
+        blob_id: &BlobId,
+        trans_handle: i32,
+        max_length: usize,
+    ) -> Result<Option<Vec<u8>>, Error> {
+        let buf = self.suspend_buffer();
+
+        self.op_open_blob(blob_id, trans_handle).await?;
+        let (blob_handle, _, _) = self.op_response().await?;
+        let info = self
+            .info_request(
+                OP_INFO_BLOB,
+                blob_handle,
+                &[ISC_INFO_BLOB_TOTAL_LENGTH as u8, ISC_INFO_END],
+            )
+            .await?;
+        let blob = match utils::parse_blob_total_length(&info) {
+            Some(length) if length > max_length as u64 => None,
+            _ => Some(self.read_blob_segments(blob_handle).await?),
+        };
+        self.close_blob(blob_handle).await?;
+
+        self.resume_buffer(&buf).await;
+        Ok(blob)
+    }
+
+    async fn read_blob_segments(&mut self, blob_handle: i32) -> Result<Vec<u8>, Error> {
         let mut blob: Vec<u8> = Vec::new();
+        let mut eof = false;
+        while !eof {
+            let (data, last) = self.read_blob_segment(blob_handle).await?;
+            blob.write(&data).await?;
+            eof = last;
+        }
+        Ok(blob)
+    }
+
+    pub(crate) async fn open_blob(
+        &mut self,
+        blob_id: &BlobId,
+        trans_handle: i32,
+    ) -> Result<i32, Error> {
         self.op_open_blob(blob_id, trans_handle).await?;
         let (blob_handle, _, _) = self.op_response().await?;
-        let mut more_data: i32 = 1;
-        while more_data != 2 {
-            self.op_get_segment(blob_handle).await?;
-            let (more_data2, _, buf) = self.op_response().await?;
-            more_data = more_data2;
-            let mut i: usize = 0;
-            while i < buf.len() {
-                let ln: usize = utils::bytes_to_uint16(&buf[i..i + 2]) as usize;
-                blob.write(&buf[i + 2..i + 2 + ln]).await?;
-                i += ln + 2;
+        Ok(blob_handle)
+    }
+
+    // segments returned by one op_get_segment, each prefixed by its 2 bytes length,
+    // and true after the last one
+    pub(crate) async fn read_blob_segment(
+        &mut self,
+        blob_handle: i32,
+    ) -> Result<(Vec<u8>, bool), Error> {
+        self.op_get_segment(blob_handle).await?;
+        let (more_data, _, buf) = self.op_response().await?;
+        let mut data: Vec<u8> = Vec::new();
+        let mut i: usize = 0;
+        while i + 2 <= buf.len() {
+            let ln: usize = utils::bytes_to_uint16(&buf[i..i + 2]) as usize;
+            if i + 2 + ln > buf.len() {
+                return Err(wireprotocol::protocol_error(format!(
+                    "blob segment length {} exceeds remaining {} bytes",
+                    ln,
+                    buf.len() - i - 2
+                )));
             }
+            data.write(&buf[i + 2..i + 2 + ln]).await?;
+            i += ln + 2;
         }
+        Ok((data, more_data == 2))
+    }
 
+    pub(crate) async fn close_blob(&mut self, blob_handle: i32) -> Result<(), Error> {
         self.op_close_blob(blob_handle).await?;
         if self.accept_type == PTYPE_LAZY_SEND {
             self.lazy_response_count += 1;
         } else {
             self.op_response().await?;
         }
-
-        self.resume_buffer(&buf).await;
-        Ok(blob)
+        Ok(())
     }
 
     pub async fn op_connect(
@@ -562,7 +763,7 @@ impl WireProtocolAsync {
         self.pack_u32(OP_CONNECT).await;
         self.pack_u32(OP_ATTACH).await;
         self.pack_u32(3).await; // CONNECT_VERSION3
-        self.pack_u32(1).await; // Arch Type(GENERIC)
+        self.pack_u32(ARCH_GENERIC as u32).await;
         self.pack_str(db_name).await;
         self.pack_u32(protocols.len() as u32).await; // protocol count
         self.pack_bytes(
@@ -637,6 +838,11 @@ impl WireProtocolAsync {
             dpb.push(self.timezone.len() as u8);
             dpb.write(&self.timezone.as_bytes()).await?;
         }
+        if !self.process_name.is_empty() {
+            dpb.push(ISC_DPB_PROCESS_NAME);
+            dpb.push(self.process_name.len() as u8);
+            dpb.write(self.process_name.as_bytes()).await?;
+        }
 
         self.pack_u32(OP_CREATE).await;
         self.pack_u32(0).await; // Database Object ID
@@ -687,6 +893,17 @@ impl WireProtocolAsync {
             dpb.push(self.timezone.len() as u8);
             dpb.write(&self.timezone.as_bytes()).await?;
         }
+        if !self.process_name.is_empty() {
+            dpb.push(ISC_DPB_PROCESS_NAME);
+            dpb.push(self.process_name.len() as u8);
+            dpb.write(self.process_name.as_bytes()).await?;
+        }
+        if self.no_linger {
+            dpb.write(&[ISC_DPB_NOLINGER, 0]).await?;
+        }
+        if self.reset_icu {
+            dpb.write(&[ISC_DPB_RESET_ICU, 0]).await?;
+        }
 
         self.pack_u32(OP_ATTACH).await;
         self.pack_u32(0).await; // Database Object ID
@@ -730,17 +947,18 @@ impl WireProtocolAsync {
 
     pub async fn op_transaction(&mut self, is_autocommit: bool) -> Result<(), Error> {
         debug_print!("op_transaction()");
+        // read_only=true attachments only start read only transactions
+        let access = if self.read_only {
+            ISC_TPB_READ
+        } else {
+            ISC_TPB_WRITE
+        };
         let tpb: Vec<u8> = if is_autocommit {
-            vec![
-                ISC_TPB_VERSION3,
-                ISC_TPB_WRITE,
-                ISC_TPB_WAIT,
-                ISC_TPB_AUTOCOMMIT,
-            ]
+            vec![ISC_TPB_VERSION3, access, ISC_TPB_WAIT, ISC_TPB_AUTOCOMMIT]
         } else {
             vec![
                 ISC_TPB_VERSION3,
-                ISC_TPB_WRITE,
+                access,
                 ISC_TPB_WAIT,
                 ISC_TPB_READ_COMMITTED,
                 ISC_TPB_NO_REC_VERSION,
@@ -755,6 +973,18 @@ impl WireProtocolAsync {
         Ok(())
     }
 
+    // attach to a transaction left in limbo by a two-phase commit, by id
+    pub async fn op_reconnect(&mut self, transaction_id: u64) -> Result<(), Error> {
+        debug_print!("op_reconnect()");
+        self.pack_u32(OP_RECONNECT).await;
+        self.pack_u32(self.db_handle as u32).await;
+        self.pack_bytes(&utils::transaction_id_bytes(transaction_id))
+            .await;
+        self.send_packets().await?;
+
+        Ok(())
+    }
+
     pub async fn op_commit(&mut self, trans_handle: i32) -> Result<(), Error> {
         debug_print!("op_commit()");
         self.pack_u32(OP_COMMIT).await;
@@ -800,28 +1030,58 @@ impl WireProtocolAsync {
         Ok(())
     }
 
-    pub async fn op_info_transaction(&mut self, trans_handle: i32, b: &[u8]) -> Result<(), Error> {
-        debug_print!("op_info_transaction()");
-        self.pack_u32(OP_INFO_TRANSACTION).await;
-        self.pack_u32(trans_handle as u32).await;
+    async fn op_info(
+        &mut self,
+        op: u32,
+        handle: i32,
+        items: &[u8],
+        buffer_len: u32,
+    ) -> Result<(), Error> {
+        self.pack_u32(op).await;
+        self.pack_u32(handle as u32).await;
         self.pack_u32(0).await;
-        self.pack_bytes(b).await;
-        self.pack_u32(BUFFER_LEN).await;
+        self.pack_bytes(items).await;
+        self.pack_u32(buffer_len).await;
         self.send_packets().await?;
 
         Ok(())
     }
 
+    // reply of an info request, sent again with a doubled buffer
+    // as long as the server answers isc_info_truncated
+    pub(crate) async fn info_request(
+        &mut self,
+        op: u32,
+        handle: i32,
+        items: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        let mut buffer_len = self.info_buffer_len;
+        loop {
+            self.op_info(op, handle, items, buffer_len).await?;
+            let (_, _, buf) = self.op_response().await?;
+            if !utils::is_info_truncated(&buf) || buffer_len >= MAX_INFO_BUFFER_LEN {
+                return Ok(buf);
+            }
+            buffer_len = buffer_len.saturating_mul(2).min(MAX_INFO_BUFFER_LEN);
+        }
+    }
+
+    pub async fn op_info_transaction(&mut self, trans_handle: i32, b: &[u8]) -> Result<(), Error> {
+        debug_print!("op_info_transaction()");
+        self.op_info(OP_INFO_TRANSACTION, trans_handle, b, self.info_buffer_len)
+            .await
+    }
+
+    pub async fn op_info_blob(&mut self, blob_handle: i32, b: &[u8]) -> Result<(), Error> {
+        debug_print!("op_info_blob()");
+        self.op_info(OP_INFO_BLOB, blob_handle, b, self.info_buffer_len)
+            .await
+    }
+
     pub async fn op_info_database(&mut self, bs: &[u8]) -> Result<(), Error> {
         debug_print!("op_info_database()");
-        self.pack_u32(OP_INFO_DATABASE).await;
-        self.pack_u32(self.db_handle as u32).await;
-        self.pack_u32(0).await;
-        self.pack_bytes(bs).await;
-        self.pack_u32(BUFFER_LEN).await;
-        self.send_packets().await?;
-
-        Ok(())
+        self.op_info(OP_INFO_DATABASE, self.db_handle, bs, self.info_buffer_len)
+            .await
     }
 
     pub async fn op_free_statement(&mut self, stmt_handle: i32, mode: i32) -> Result<(), Error> {
@@ -851,7 +1111,7 @@ impl WireProtocolAsync {
         self.pack_u32(3).await; // dialect = 3
         self.pack_str(query).await;
         self.pack_bytes(&bs).await;
-        self.pack_u32(BUFFER_LEN).await;
+        self.pack_u32(self.info_buffer_len).await;
         self.send_packets().await?;
 
         Ok(())
@@ -859,14 +1119,8 @@ impl WireProtocolAsync {
 
     pub async fn op_info_sql(&mut self, stmt_handle: i32, vars: &[u8]) -> Result<(), Error> {
         debug_print!("op_info_sql()");
-        self.pack_u32(OP_INFO_SQL).await;
-        self.pack_u32(stmt_handle as u32).await;
-        self.pack_u32(0).await;
-        self.pack_bytes(&vars).await;
-        self.pack_u32(BUFFER_LEN).await;
-        self.send_packets().await?;
-
-        Ok(())
+        self.op_info(OP_INFO_SQL, stmt_handle, vars, self.info_buffer_len)
+            .await
     }
 
     pub async fn op_execute(
@@ -944,7 +1198,7 @@ impl WireProtocolAsync {
         self.pack_u32(3).await; // dialect = 3
         self.pack_str(query).await;
         self.pack_bytes(&desc_items).await;
-        self.pack_u32(BUFFER_LEN).await;
+        self.pack_u32(self.info_buffer_len).await;
         self.send_packets().await?;
         Ok(())
     }
@@ -960,10 +1214,7 @@ impl WireProtocolAsync {
         Ok(())
     }
 
-    pub async fn op_fetch_response(
-        &mut self,
-        xsqlda: &[XSQLVar],
-    ) -> Result<(Vec<Vec<CellValue>>, bool), Error> {
+    pub async fn op_fetch_response(&mut self, xsqlda: &[XSQLVar]) -> Result<FetchResponse, Error> {
         debug_print!("op_fetch_response()");
         let mut opcode = utils::bytes_to_buint32(&self.recv_packets(4).await?);
         while opcode == OP_DUMMY {
@@ -976,30 +1227,33 @@ impl WireProtocolAsync {
         }
 
         if opcode != OP_FETCH_RESPONSE {
-            self.parse_op_response().await?;
-            panic!("op fetch response error"); // not reach
+            if opcode == OP_RESPONSE {
+                self.parse_op_response().await?; // error
+            }
+            return Err(wireprotocol::protocol_error(format!(
+                "unexpected opcode {} waiting for op_fetch_response",
+                opcode
+            )));
         }
 
         let mut status = utils::bytes_to_buint32(&self.recv_packets(4).await?);
         let mut count = utils::bytes_to_buint32(&self.recv_packets(4).await?);
         let mut rows: Vec<Vec<CellValue>> = Vec::new();
         let xsqlda_len = xsqlda.len();
+        // after a value can't be decoded, the rest of the packet is read and dropped
+        let mut error: Option<Error> = None;
 
         while count > 0 {
             let mut n = xsqlda_len / 8;
             if xsqlda_len % 8 != 0 {
                 n += 1;
             }
-            let mut null_indicator: u128 = 0;
-            let b = &self.recv_packets_alignment(n).await?;
-            for c in b.iter().rev() {
-                null_indicator <<= 8;
-                null_indicator += *c as u128;
-            }
+            // a bit per column, so more than 128 columns fit
+            let null_indicator = self.recv_packets_alignment(n).await?;
 
             let mut row: Vec<CellValue> = Vec::with_capacity(xsqlda_len);
             for (i, x) in xsqlda.iter().enumerate() {
-                if (null_indicator & (1 << i)) != 0 {
+                if null_indicator[i / 8] & (1 << (i % 8)) != 0 {
                     row.push(CellValue::Null)
                 } else {
                     let ln = if x.io_length() < 0 {
@@ -1008,16 +1262,47 @@ impl WireProtocolAsync {
                         x.io_length() as usize
                     };
                     let raw_value = self.recv_packets_alignment(ln as usize).await?;
-                    row.push(x.value(&raw_value)?);
+                    if error.is_none() {
+                        match x.column_value(i, &raw_value) {
+                            Ok(v) => row.push(v),
+                            Err(e) => error = Some(Error::ValueError(e)),
+                        }
+                    }
                 }
             }
-            rows.push(row);
-            let _op_code = utils::bytes_to_buint32(&self.recv_packets(4).await?);
+            if error.is_none() {
+                rows.push(row);
+            }
+            let opcode = utils::bytes_to_buint32(&self.recv_packets(4).await?);
+            if opcode == OP_RESPONSE {
+                // the server failed after the rows before it, they are kept
+                let e = match self.parse_op_response().await {
+                    Err(e) => e,
+                    Ok(_) => wireprotocol::protocol_error(format!(
+                        "unexpected opcode {} in op_fetch_response",
+                        opcode
+                    )),
+                };
+                return Ok((rows, status, error.or(Some(e))));
+            }
             status = utils::bytes_to_buint32(&self.recv_packets(4).await?);
             count = utils::bytes_to_buint32(&self.recv_packets(4).await?);
         }
 
-        Ok((rows, status != 100))
+        if status != FETCH_OK && status != FETCH_NO_MORE_ROWS && error.is_none() {
+            error = Some(wireprotocol::protocol_error(format!(
+                "unexpected op_fetch_response status {}",
+                status
+            )));
+        }
+        Ok((rows, status, error))
+    }
+
+    pub async fn op_ping(&mut self) -> Result<(), Error> {
+        debug_print!("op_ping()");
+        self.pack_u32(OP_PING).await;
+        self.send_packets().await?;
+        Ok(())
     }
 
     pub async fn op_detach(&mut self) -> Result<(), Error> {
@@ -1028,15 +1313,65 @@ impl WireProtocolAsync {
         Ok(())
     }
 
-    pub async fn op_open_blob(
-        &mut self,
-        blob_id: &Vec<u8>,
-        trans_handle: i32,
-    ) -> Result<(), Error> {
+    // request the auxiliary connection which events are delivered on
+    pub async fn op_connect_request(&mut self) -> Result<(), Error> {
+        debug_print!("op_connect_request()");
+        self.pack_u32(OP_CONNECT_REQUEST).await;
+        self.pack_u32(P_REQ_ASYNC).await;
+        self.pack_u32(self.db_handle as u32).await;
+        self.pack_u32(0).await;
+        self.send_packets().await?;
+        Ok(())
+    }
+
+    // connect to the port the server listens on for the auxiliary connection,
+    // from the bind_address of the connection. The TLS proxy in front of the
+    // server doesn't forward that port, events aren't supported with tls
+    pub(crate) async fn event_channel(&mut self) -> Result<WireChannelAsync, Error> {
+        if self.tls {
+            return Err(Error::UrlError(UrlError::Unsupported(
+                "events are not supported over tls".to_string(),
+            )));
+        }
+        self.op_connect_request().await?;
+        let (_, _, buf) = self.op_response().await?;
+        // struct sockaddr, the port in network byte order
+        if buf.len() < 4 {
+            return Err(wireprotocol::protocol_error(format!(
+                "auxiliary address of {} bytes",
+                buf.len()
+            )));
+        }
+        let port = utils::bytes_to_buint16(&buf[2..4]);
+        WireChannelAsync::new(&self.host, port, false, &self.bind_address).await
+    }
+
+    pub async fn op_que_events(&mut self, event_block: &[u8], event_id: i32) -> Result<(), Error> {
+        debug_print!("op_que_events()");
+        self.pack_u32(OP_QUE_EVENTS).await;
+        self.pack_u32(self.db_handle as u32).await;
+        self.pack_bytes(event_block).await;
+        self.pack_u32(0).await; // ast
+        self.pack_u32(0).await; // args
+        self.pack_u32(event_id as u32).await;
+        self.send_packets().await?;
+        Ok(())
+    }
+
+    pub async fn op_cancel_events(&mut self, event_id: i32) -> Result<(), Error> {
+        debug_print!("op_cancel_events()");
+        self.pack_u32(OP_CANCEL_EVENTS).await;
+        self.pack_u32(self.db_handle as u32).await;
+        self.pack_u32(event_id as u32).await;
+        self.send_packets().await?;
+        Ok(())
+    }
+
+    pub async fn op_open_blob(&mut self, blob_id: &BlobId, trans_handle: i32) -> Result<(), Error> {
         debug_print!("op_open_blob()");
         self.pack_u32(OP_OPEN_BLOB).await;
         self.pack_u32(trans_handle as u32).await;
-        self.append_bytes(blob_id).await;
+        self.append_bytes(&blob_id.0).await;
         self.send_packets().await?;
         Ok(())
     }
@@ -1106,6 +1441,14 @@ impl WireProtocolAsync {
         Ok(())
     }
 
+    pub async fn op_cancel_blob(&mut self, blob_handle: i32) -> Result<(), Error> {
+        debug_print!("op_cancel_blob()");
+        self.pack_u32(OP_CANCEL_BLOB).await;
+        self.pack_u32(blob_handle as u32).await;
+        self.send_packets().await?;
+        Ok(())
+    }
+
     pub async fn op_response(&mut self) -> Result<(i32, Vec<u8>, Vec<u8>), Error> {
         debug_print!("op_response()");
         let mut opcode = utils::bytes_to_buint32(&self.recv_packets(4).await?);
@@ -1128,7 +1471,10 @@ impl WireProtocolAsync {
         }
     }
 
-    async fn op_sql_response(&mut self, xsqlda: &[XSQLVar]) -> Result<Vec<CellValue>, Error> {
+    pub(crate) async fn op_sql_response(
+        &mut self,
+        xsqlda: &[XSQLVar],
+    ) -> Result<Vec<CellValue>, Error> {
         debug_print!("op_sql_response()");
         let xsqlda_len = xsqlda.len();
         let mut row: Vec<CellValue> = Vec::with_capacity(xsqlda_len);
@@ -1136,9 +1482,19 @@ impl WireProtocolAsync {
         while opcode == OP_DUMMY {
             opcode = utils::bytes_to_buint32(&self.recv_packets(4).await?);
         }
-        if opcode == OP_RESPONSE {
-            self.parse_op_response().await?; // error
-            panic!("sql response error"); // not reach
+        while opcode == OP_RESPONSE && self.lazy_response_count > 0 {
+            self.lazy_response_count -= 1;
+            self.parse_op_response().await?;
+            opcode = utils::bytes_to_buint32(&self.recv_packets(4).await?);
+        }
+        if opcode != OP_SQL_RESPONSE {
+            if opcode == OP_RESPONSE {
+                self.parse_op_response().await?; // error
+            }
+            return Err(wireprotocol::protocol_error(format!(
+                "unexpected opcode {} waiting for op_sql_response",
+                opcode
+            )));
         }
 
         let count = utils::bytes_to_buint32(&self.recv_packets(4).await?);
@@ -1148,14 +1504,10 @@ impl WireProtocolAsync {
                 n += 1;
             }
 
-            let mut null_indicator: u128 = 0;
-            let b = &self.recv_packets_alignment(n).await?;
-            for c in b.iter().rev() {
-                null_indicator <<= 8;
-                null_indicator += *c as u128
-            }
+            // a bit per column, so more than 128 columns fit
+            let null_indicator = self.recv_packets_alignment(n).await?;
             for (i, x) in xsqlda.iter().enumerate() {
-                if null_indicator & (1 << i) != 0 {
+                if null_indicator[i / 8] & (1 << (i % 8)) != 0 {
                     row.push(CellValue::Null)
                 } else {
                     let ln = if x.io_length() < 0 {
@@ -1164,7 +1516,7 @@ impl WireProtocolAsync {
                         x.io_length() as usize
                     };
                     let raw_value = self.recv_packets_alignment(ln as usize).await?;
-                    row.push(x.value(&raw_value)?);
+                    row.push(x.column_value(i, &raw_value)?);
                 }
             }
         }
@@ -1172,58 +1524,80 @@ impl WireProtocolAsync {
         Ok(row)
     }
 
-    pub async fn create_blob(&mut self, value: &[u8], trans_handle: i32) -> Result<Vec<u8>, Error> {
+    // stream the reader into a new blob segment by segment
+    pub async fn create_blob<R: std::io::Read>(
+        &mut self,
+        reader: &mut R,
+        trans_handle: i32,
+    ) -> Result<BlobId, Error> {
         let buf = self.suspend_buffer();
-        let blob_handle: i32;
-        let blob_id: Vec<u8>;
         self.op_create_blob2(trans_handle).await?;
 
-        match self.op_response().await {
-            Ok((h, id, _)) => {
-                blob_handle = h;
-                blob_id = id;
-            }
+        let (blob_handle, id) = match self.op_response().await {
+            Ok((h, id, _)) => (h, id),
             Err(e) => {
                 self.resume_buffer(&buf).await;
                 return Err(e);
             }
-        }
-
-        let mut i: usize = 0;
-        while i < value.len() {
-            let mut end: usize = i + BLOB_SEGMENT_SIZE;
-            if end > value.len() {
-                end = value.len()
-            }
-            self.op_put_segment(blob_handle, &value[i..end]).await?;
-            self.op_response().await?;
-            i += BLOB_SEGMENT_SIZE;
-        }
+        };
 
+        let written = match BlobId::from_slice(&id) {
+            Ok(blob_id) => self
+                .put_blob_segments(blob_handle, reader)
+                .await
+                .map(|_| blob_id),
+            Err(e) => Err(e.into()),
+        };
         self.resume_buffer(&buf).await;
+        let blob_id = match written {
+            Ok(blob_id) => blob_id,
+            Err(e) => {
+                // discard the half written blob, the connection stays usable
+                if self.op_cancel_blob(blob_handle).await.is_ok() {
+                    let _ = self.op_response().await;
+                }
+                return Err(e);
+            }
+        };
         self.op_close_blob(blob_handle).await?;
         self.op_response().await?;
 
         Ok(blob_id)
     }
 
+    async fn put_blob_segments<R: std::io::Read>(
+        &mut self,
+        blob_handle: i32,
+        reader: &mut R,
+    ) -> Result<(), Error> {
+        let mut segment = vec![0; BLOB_SEGMENT_SIZE];
+        loop {
+            let n = reader.read(&mut segment)?;
+            if n == 0 {
+                return Ok(());
+            }
+            self.op_put_segment(blob_handle, &segment[..n]).await?;
+            self.op_response().await?;
+        }
+    }
+
     async fn params_to_blr(
         &mut self,
         params: &[(Vec<u8>, Vec<u8>, bool)],
     ) -> Result<(Vec<u8>, Vec<u8>), Error> {
+        if params.len() > MAX_PARAMS {
+            return Err(Error::ParamError(ParamError::new(&format!(
+                "too many parameters: {} (max {})",
+                params.len(),
+                MAX_PARAMS
+            ))));
+        }
         let mut values_list: Vec<u8> = Vec::new();
         let mut blr_list: Vec<u8> = Vec::new();
         let ln = params.len() * 2;
         let blr = vec![5, 2, 4, 0, (ln & 0xFF) as u8, ((ln >> 8) & 0xFF) as u8];
         blr_list.write(&blr).await?;
 
-        let mut null_indicator: u128 = 0;
-        for (i, (_value, _blr, isnull)) in params.iter().enumerate() {
-            if *isnull {
-                null_indicator |= 1 << i;
-            }
-        }
-
         let mut n = params.len() / 8;
         if params.len() % 8 != 0 {
             n += 1;
@@ -1233,10 +1607,13 @@ impl WireProtocolAsync {
             n += 4 - n % 4;
         }
 
-        for _ in 0..n {
-            values_list.push((null_indicator & 255) as u8);
-            null_indicator >>= 8;
+        let mut null_indicator = vec![0u8; n];
+        for (i, (_value, _blr, isnull)) in params.iter().enumerate() {
+            if *isnull {
+                null_indicator[i / 8] |= 1 << (i % 8);
+            }
         }
+        values_list.write(&null_indicator).await?;
 
         for p in params.iter() {
             values_list.write(&p.0).await?;
@@ -1249,9 +1626,47 @@ impl WireProtocolAsync {
     }
 }
 
+// next op_event on the auxiliary connection, (event id, counts by name)
+pub(crate) async fn recv_event(
+    channel: &mut WireChannelAsync,
+) -> Result<(i32, HashMap<String, u32>), Error> {
+    let mut opcode = utils::bytes_to_buint32(&channel.read(4).await?);
+    while opcode == OP_DUMMY {
+        opcode = utils::bytes_to_buint32(&channel.read(4).await?);
+    }
+    if opcode == OP_EXIT || opcode == OP_DISCONNECT {
+        return Err(Error::IoError(std::io::ErrorKind::ConnectionAborted.into()));
+    }
+    if opcode != OP_EVENT {
+        return Err(wireprotocol::protocol_error(format!(
+            "opcode {} for op_event",
+            opcode
+        )));
+    }
+    channel.read(4).await?; // db_handle
+    let nbytes = utils::bytes_to_buint32(&channel.read(4).await?) as usize;
+    let buf = channel.read(nbytes).await?;
+    channel.read((4 - nbytes % 4) % 4).await?; // padding
+    channel.read(8).await?; // ast
+    let event_id = utils::bytes_to_bint32(&channel.read(4).await?);
+    match utils::parse_event_block(&buf) {
+        Some(counts) => Ok((event_id, counts)),
+        None => Err(wireprotocol::protocol_error(
+            "malformed event block".to_string(),
+        )),
+    }
+}
+
 impl Drop for WireProtocolAsync {
     fn drop(&mut self) {
-        let _ = task::block_on(self.op_detach());
-        let _ = task::block_on(self.op_response());
+        // Drops of the async values don't block_on() the server, drop may run
+        // inside an async task where that panics or stalls the executor. Each
+        // value is ended by an awaited method (close(), release(), cancel()),
+        // a dropped one is ended with the next request (defer_request()) or
+        // left to the server. Here the server releases the attachment when the
+        // socket is closed.
+        if !self.closed {
+            debug_print!("WireProtocolAsync dropped without ConnectionAsync::close()");
+        }
     }
 }
@@ -50,6 +50,90 @@ pub const SQL_TYPE_DEC128: u32 = 32762;
 pub const SQL_TYPE_BOOLEAN: u32 = 32764;
 pub const SQL_TYPE_NULL: u32 = 32766;
 
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum SqlType {
+    Text,
+    Varying,
+    Short,
+    Long,
+    Float,
+    Double,
+    DFloat,
+    Timestamp,
+    Blob,
+    Array,
+    Quad,
+    Time,
+    Date,
+    Int64,
+    Int128,
+    TimestampTz,
+    TimeTz,
+    DecFixed,
+    Dec64,
+    Dec128,
+    Boolean,
+    Null,
+}
+
+impl SqlType {
+    pub fn from_u32(sqltype: u32) -> Option<SqlType> {
+        match sqltype {
+            SQL_TYPE_TEXT => Some(SqlType::Text),
+            SQL_TYPE_VARYING => Some(SqlType::Varying),
+            SQL_TYPE_SHORT => Some(SqlType::Short),
+            SQL_TYPE_LONG => Some(SqlType::Long),
+            SQL_TYPE_FLOAT => Some(SqlType::Float),
+            SQL_TYPE_DOUBLE => Some(SqlType::Double),
+            SQL_TYPE_D_FLOAT => Some(SqlType::DFloat),
+            SQL_TYPE_TIMESTAMP => Some(SqlType::Timestamp),
+            SQL_TYPE_BLOB => Some(SqlType::Blob),
+            SQL_TYPE_ARRAY => Some(SqlType::Array),
+            SQL_TYPE_QUAD => Some(SqlType::Quad),
+            SQL_TYPE_TIME => Some(SqlType::Time),
+            SQL_TYPE_DATE => Some(SqlType::Date),
+            SQL_TYPE_INT64 => Some(SqlType::Int64),
+            SQL_TYPE_INT128 => Some(SqlType::Int128),
+            SQL_TYPE_TIMESTAMP_TZ => Some(SqlType::TimestampTz),
+            SQL_TYPE_TIME_TZ => Some(SqlType::TimeTz),
+            SQL_TYPE_DEC_FIXED => Some(SqlType::DecFixed),
+            SQL_TYPE_DEC64 => Some(SqlType::Dec64),
+            SQL_TYPE_DEC128 => Some(SqlType::Dec128),
+            SQL_TYPE_BOOLEAN => Some(SqlType::Boolean),
+            SQL_TYPE_NULL => Some(SqlType::Null),
+            _ => None,
+        }
+    }
+
+    pub fn as_u32(&self) -> u32 {
+        match self {
+            SqlType::Text => SQL_TYPE_TEXT,
+            SqlType::Varying => SQL_TYPE_VARYING,
+            SqlType::Short => SQL_TYPE_SHORT,
+            SqlType::Long => SQL_TYPE_LONG,
+            SqlType::Float => SQL_TYPE_FLOAT,
+            SqlType::Double => SQL_TYPE_DOUBLE,
+            SqlType::DFloat => SQL_TYPE_D_FLOAT,
+            SqlType::Timestamp => SQL_TYPE_TIMESTAMP,
+            SqlType::Blob => SQL_TYPE_BLOB,
+            SqlType::Array => SQL_TYPE_ARRAY,
+            SqlType::Quad => SQL_TYPE_QUAD,
+            SqlType::Time => SQL_TYPE_TIME,
+            SqlType::Date => SQL_TYPE_DATE,
+            SqlType::Int64 => SQL_TYPE_INT64,
+            SqlType::Int128 => SQL_TYPE_INT128,
+            SqlType::TimestampTz => SQL_TYPE_TIMESTAMP_TZ,
+            SqlType::TimeTz => SQL_TYPE_TIME_TZ,
+            SqlType::DecFixed => SQL_TYPE_DEC_FIXED,
+            SqlType::Dec64 => SQL_TYPE_DEC64,
+            SqlType::Dec128 => SQL_TYPE_DEC128,
+            SqlType::Boolean => SQL_TYPE_BOOLEAN,
+            SqlType::Null => SQL_TYPE_NULL,
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct XSQLVar {
     pub sqltype: u32,
     pub sqlscale: i32,
@@ -89,6 +173,7 @@ impl XSQLVar {
                 SQL_TYPE_TIME=>         4,
                 SQL_TYPE_DATE=>         4,
                 SQL_TYPE_DOUBLE=>       8,
+                SQL_TYPE_D_FLOAT=>      8,
                 SQL_TYPE_TIMESTAMP=>    8,
                 SQL_TYPE_BLOB=>         8,
                 SQL_TYPE_ARRAY=>        8,
@@ -101,14 +186,22 @@ impl XSQLVar {
                 SQL_TYPE_DEC128=>       16,
                 SQL_TYPE_DEC_FIXED=>    16,
                 SQL_TYPE_BOOLEAN=>      1,
+                SQL_TYPE_NULL=>         0,
             };
             map[&self.sqltype]
         }
     }
 
     pub fn value(&self, raw_value: &[u8]) -> Result<CellValue, ValueError> {
+        // binary strings, not valid UTF-8
+        if (self.sqltype == SQL_TYPE_TEXT || self.sqltype == SQL_TYPE_VARYING)
+            && self.sqlsubtype & 0xff == 1
+        {
+            return Ok(CellValue::Octets(raw_value.to_vec()));
+        }
         match self.sqltype {
             SQL_TYPE_TEXT => Ok(CellValue::Text(bytes_to_rtrim_str(raw_value))),
+            SQL_TYPE_NULL => Ok(CellValue::Null),
             SQL_TYPE_VARYING => Ok(CellValue::Varying(bytes_to_str(raw_value))),
             SQL_TYPE_SHORT => Ok(CellValue::Short(bytes_to_bint16(raw_value))),
             SQL_TYPE_LONG => Ok(CellValue::Long(bytes_to_bint32(raw_value))),
@@ -126,10 +219,15 @@ impl XSQLVar {
                 CellValue::Int64(bytes_to_bint64(raw_value))
             }),
             SQL_TYPE_INT128 => Ok(if self.sqlscale < 0 {
-                CellValue::Decimal(rust_decimal::Decimal::new(
-                    bytes_to_bint64(raw_value),
+                // NUMERIC(38) beyond the 96 bit mantissa of Decimal is kept as is
+                let n = bytes_to_bint128(raw_value);
+                match rust_decimal::Decimal::try_from_i128_with_scale(
+                    n,
                     (self.sqlscale * -1) as u32,
-                ))
+                ) {
+                    Ok(d) => CellValue::Decimal(d),
+                    Err(_) => CellValue::Scaled(n, self.sqlscale),
+                }
             } else if self.sqlscale > 0 {
                 CellValue::Decimal(rust_decimal::Decimal::new(
                     (bytes_to_bint128(raw_value) as i64) * (self.sqlscale as i64),
@@ -145,6 +243,9 @@ impl XSQLVar {
             SQL_TYPE_TIMESTAMP_TZ => Ok(CellValue::TimeStampTz(bytes_to_date_time_tz(raw_value))),
             SQL_TYPE_FLOAT => Ok(CellValue::Float(bytes_to_f32(raw_value))),
             SQL_TYPE_DOUBLE => Ok(CellValue::Double(bytes_to_f64(raw_value))),
+            // legacy VAX D_floating, the server sends it as an XDR double so
+            // the value is limited to IEEE double precision (53 bit mantissa)
+            SQL_TYPE_D_FLOAT => Ok(CellValue::Double(bytes_to_f64(raw_value))),
             SQL_TYPE_BOOLEAN => Ok(CellValue::Boolean(raw_value[0] != 0)),
             SQL_TYPE_BLOB => Ok(if self.sqlsubtype == 1 {
                 CellValue::BlobText(raw_value.to_vec())
@@ -167,4 +268,303 @@ impl XSQLVar {
             ))),
         }
     }
+
+    pub fn type_name(&self) -> &'static str {
+        match self.sqltype {
+            SQL_TYPE_TEXT => "CHAR",
+            SQL_TYPE_VARYING => "VARCHAR",
+            // sqlsubtype of exact numerics is 1: NUMERIC, 2: DECIMAL
+            SQL_TYPE_SHORT | SQL_TYPE_LONG | SQL_TYPE_INT64 | SQL_TYPE_INT128
+            | SQL_TYPE_DEC_FIXED
+                if self.sqlsubtype == 2 =>
+            {
+                "DECIMAL"
+            }
+            SQL_TYPE_SHORT | SQL_TYPE_LONG | SQL_TYPE_INT64 | SQL_TYPE_INT128
+            | SQL_TYPE_DEC_FIXED
+                if self.sqlsubtype == 1 || self.sqlscale < 0 =>
+            {
+                "NUMERIC"
+            }
+            SQL_TYPE_SHORT => "SMALLINT",
+            SQL_TYPE_LONG => "INTEGER",
+            SQL_TYPE_INT64 => "BIGINT",
+            SQL_TYPE_INT128 => "INT128",
+            SQL_TYPE_FLOAT => "FLOAT",
+            SQL_TYPE_DOUBLE | SQL_TYPE_D_FLOAT => "DOUBLE PRECISION",
+            SQL_TYPE_DATE => "DATE",
+            SQL_TYPE_TIME => "TIME",
+            SQL_TYPE_TIMESTAMP => "TIMESTAMP",
+            SQL_TYPE_TIME_TZ => "TIME WITH TIME ZONE",
+            SQL_TYPE_TIMESTAMP_TZ => "TIMESTAMP WITH TIME ZONE",
+            SQL_TYPE_BLOB => "BLOB",
+            SQL_TYPE_ARRAY => "ARRAY",
+            SQL_TYPE_QUAD => "QUAD",
+            SQL_TYPE_DEC_FIXED => "DECIMAL",
+            SQL_TYPE_DEC64 => "DECFLOAT(16)",
+            SQL_TYPE_DEC128 => "DECFLOAT(34)",
+            SQL_TYPE_BOOLEAN => "BOOLEAN",
+            SQL_TYPE_NULL => "NULL",
+            _ => "UNKNOWN",
+        }
+    }
+
+    // The describe doesn't carry the declared precision of NUMERIC/DECIMAL,
+    // this is the largest precision of its storage type.
+    pub fn precision(&self) -> i32 {
+        match self.sqltype {
+            SQL_TYPE_TEXT | SQL_TYPE_VARYING => self.char_length(),
+            SQL_TYPE_SHORT => 4,
+            SQL_TYPE_LONG => 9,
+            SQL_TYPE_INT64 => 18,
+            SQL_TYPE_INT128 => 38,
+            SQL_TYPE_FLOAT => 7,
+            SQL_TYPE_DOUBLE | SQL_TYPE_D_FLOAT => 15,
+            SQL_TYPE_DEC_FIXED | SQL_TYPE_DEC128 => 34,
+            SQL_TYPE_DEC64 => 16,
+            SQL_TYPE_DATE => 10,
+            SQL_TYPE_TIME => 8,
+            SQL_TYPE_TIMESTAMP => 19,
+            SQL_TYPE_BOOLEAN => 1,
+            _ => 0,
+        }
+    }
+
+    // sqllen of CHAR/VARCHAR is in bytes, the declared length is in characters
+    pub fn char_length(&self) -> i32 {
+        // sqlsubtype of text columns is the character set id
+        let bytes_per_char = match self.sqlsubtype & 0xff {
+            3 => 3,                              // UNICODE_FSS
+            4 | 69 => 4,                         // UTF8, GB18030
+            5 | 6 | 44 | 56 | 57 | 67 | 68 => 2, // SJIS_0208, EUCJ_0208, KSC_5601, BIG_5, GB_2312, GBK, CP943C
+            _ => 1,
+        };
+        self.sqllen / bytes_per_char
+    }
+
+    // value() with the column index and alias name in the error message
+    pub fn column_value(&self, index: usize, raw_value: &[u8]) -> Result<CellValue, ValueError> {
+        self.value(raw_value).map_err(|e| {
+            ValueError::new(&format!(
+                "column {} ({}): {}",
+                index, self.aliasname, e.message
+            ))
+        })
+    }
+}
+
+// blr of the output message for op_fetch
+pub fn calc_blr(xsqlda: &[XSQLVar]) -> Result<Vec<u8>, ValueError> {
+    let ln = xsqlda.len() * 2;
+    let mut blr: Vec<u8> = vec![5, 2, 4, 0, (ln & 255) as u8, (ln >> 8) as u8];
+
+    for (i, x) in xsqlda.iter().enumerate() {
+        let map = hashmap! {
+            SQL_TYPE_VARYING => vec![37, (x.sqllen & 255) as u8, (x.sqllen >> 8) as u8],
+            SQL_TYPE_TEXT => vec![14, (x.sqllen & 255) as u8, (x.sqllen >> 8) as u8],
+            SQL_TYPE_LONG => vec![8, x.sqlscale as u8],
+            SQL_TYPE_SHORT => vec![7, x.sqlscale as u8],
+            SQL_TYPE_INT64 => vec![16, x.sqlscale as u8],
+            SQL_TYPE_INT128 => vec![26, x.sqlscale as u8],
+            SQL_TYPE_QUAD => vec![9, x.sqlscale as u8],
+            SQL_TYPE_DEC_FIXED => vec![26, x.sqlscale as u8],
+            SQL_TYPE_DOUBLE => vec![27],
+            SQL_TYPE_FLOAT => vec![10],
+            SQL_TYPE_D_FLOAT => vec![11],
+            SQL_TYPE_DATE => vec![12],
+            SQL_TYPE_TIME => vec![13],
+            SQL_TYPE_TIMESTAMP => vec![35],
+            SQL_TYPE_BLOB => vec![9, 0],
+            SQL_TYPE_ARRAY => vec![9, 0],
+            SQL_TYPE_BOOLEAN => vec![23],
+            SQL_TYPE_DEC64 => vec![24],
+            SQL_TYPE_DEC128 => vec![25],
+            SQL_TYPE_TIME_TZ => vec![28],
+            SQL_TYPE_TIMESTAMP_TZ => vec![29],
+            SQL_TYPE_NULL => vec![14, 0, 0],
+        };
+        match map.get(&x.sqltype) {
+            Some(b) => blr.extend(b),
+            None => {
+                return Err(ValueError::new(&format!(
+                    "column {} ({}): unsupported column type {}",
+                    i, x.aliasname, x.sqltype
+                )))
+            }
+        }
+        blr.extend(vec![7, 0]);
+    }
+    blr.extend(vec![255, 76]);
+
+    Ok(blr)
+}
+
+#[test]
+fn test_column_value_error() {
+    let mut x = XSQLVar::new();
+    x.sqltype = 0;
+    x.aliasname = "AMOUNT".to_string();
+    assert_eq!(
+        x.column_value(12, &[]).unwrap_err().message,
+        "column 12 (AMOUNT): can't parse result value:0"
+    );
+
+    x.sqltype = SQL_TYPE_SHORT;
+    assert!(x.column_value(12, &[0, 1]).is_ok());
+}
+
+#[test]
+fn test_d_float_value() {
+    let mut x = XSQLVar::new();
+    x.sqltype = SQL_TYPE_D_FLOAT;
+    assert_eq!(x.io_length(), 8);
+    assert_eq!(
+        x.value(&1.5f64.to_be_bytes()).unwrap(),
+        CellValue::Double(1.5)
+    );
+}
+
+#[test]
+fn test_char_length() {
+    let mut x = XSQLVar::new();
+    x.sqltype = SQL_TYPE_TEXT;
+    x.sqllen = 40;
+    assert_eq!(x.char_length(), 40);
+    x.sqlsubtype = 4; // UTF8
+    assert_eq!(x.char_length(), 10);
+    assert_eq!(x.precision(), 10);
+    x.sqlsubtype = 3; // UNICODE_FSS
+    x.sqllen = 30;
+    assert_eq!(x.char_length(), 10);
+}
+
+#[test]
+fn test_octets_value() {
+    let mut x = XSQLVar::new();
+    x.sqltype = SQL_TYPE_TEXT;
+    x.sqllen = 4;
+    x.sqlsubtype = 1; // OCTETS
+    assert_eq!(
+        x.value(&[0xde, 0xad, 0, 0]).unwrap(),
+        CellValue::Octets(vec![0xde, 0xad, 0, 0])
+    );
+    x.sqltype = SQL_TYPE_VARYING;
+    assert_eq!(x.value(&[0xff]).unwrap(), CellValue::Octets(vec![0xff]));
+}
+
+#[test]
+fn test_int128_value() {
+    let mut x = XSQLVar::new();
+    x.sqltype = SQL_TYPE_INT128;
+    assert_eq!(x.io_length(), 16);
+    assert_eq!(
+        x.value(&bint128_to_bytes(i128::MAX)).unwrap(),
+        CellValue::Int128(i128::MAX)
+    );
+}
+
+#[test]
+fn test_int128_scaled_value() {
+    let mut x = XSQLVar::new();
+    x.sqltype = SQL_TYPE_INT128;
+    x.sqlscale = -2;
+    assert_eq!(
+        x.value(&bint128_to_bytes(-12345)).unwrap(),
+        CellValue::Decimal(rust_decimal::Decimal::new(-12345, 2))
+    );
+    // 38 digits
+    let n = 12345678901234567890123456789012345678;
+    assert_eq!(
+        x.value(&bint128_to_bytes(n)).unwrap(),
+        CellValue::Scaled(n, -2)
+    );
+}
+
+#[test]
+fn test_time_zone_values() {
+    use chrono::TimeZone;
+    let tokyo: chrono_tz::Tz = "Asia/Tokyo".parse().unwrap();
+    let utc: chrono_tz::Tz = "UTC".parse().unwrap();
+    let mut x = XSQLVar::new();
+
+    // 03:34:56 UTC in Asia/Tokyo (65211, an XDR short)
+    x.sqltype = SQL_TYPE_TIME_TZ;
+    assert_eq!(x.io_length(), 8);
+    let mut raw = convert_time(3, 34, 56, 0).to_vec();
+    raw.extend([0xff, 0xff, 0xfe, 0xbb]);
+    assert_eq!(
+        x.value(&raw).unwrap(),
+        CellValue::TimeTz((chrono::NaiveTime::from_hms_opt(12, 34, 56).unwrap(), tokyo))
+    );
+
+    // 1967-08-11 14:45:01 UTC in Asia/Tokyo
+    x.sqltype = SQL_TYPE_TIMESTAMP_TZ;
+    assert_eq!(x.io_length(), 12);
+    let mut raw = convert_date(1967, 8, 11).unwrap().to_vec();
+    raw.extend(convert_time(14, 45, 1, 0));
+    raw.extend([0xff, 0xff, 0xfe, 0xbb]);
+    let expected = tokyo.with_ymd_and_hms(1967, 8, 11, 23, 45, 1).unwrap();
+    match x.value(&raw).unwrap() {
+        CellValue::TimeStampTz(v) => {
+            assert_eq!(v, expected);
+            assert_eq!(v.timezone(), tokyo);
+        }
+        v => panic!("{:?}", v),
+    }
+
+    // an offset zone (+05:30 is 1769) has no chrono_tz zone, the instant is kept in UTC
+    let mut raw = convert_date(1967, 8, 11).unwrap().to_vec();
+    raw.extend(convert_time(14, 45, 1, 0));
+    raw.extend([0, 0, 0x06, 0xe9]);
+    match x.value(&raw).unwrap() {
+        CellValue::TimeStampTz(v) => {
+            assert_eq!(v, expected);
+            assert_eq!(v.timezone(), utc);
+        }
+        v => panic!("{:?}", v),
+    }
+}
+
+#[test]
+fn test_null_value() {
+    let mut x = XSQLVar::new();
+    x.sqltype = SQL_TYPE_NULL;
+    assert_eq!(x.io_length(), 0);
+    assert_eq!(x.value(&[]).unwrap(), CellValue::Null);
+}
+
+#[test]
+fn test_calc_blr() {
+    let mut a = XSQLVar::new();
+    a.sqltype = SQL_TYPE_LONG;
+    let mut b = XSQLVar::new();
+    b.sqltype = SQL_TYPE_NULL;
+    assert_eq!(
+        calc_blr(&[a.clone(), b]).unwrap(),
+        vec![5, 2, 4, 0, 4, 0, 8, 0, 7, 0, 14, 0, 0, 7, 0, 255, 76]
+    );
+
+    let mut c = XSQLVar::new();
+    c.sqltype = 12345;
+    c.aliasname = "C".to_string();
+    assert_eq!(
+        calc_blr(&[a, c]).unwrap_err().message,
+        "column 1 (C): unsupported column type 12345"
+    );
+}
+
+#[test]
+fn test_sql_type() {
+    assert_eq!(SqlType::from_u32(SQL_TYPE_VARYING), Some(SqlType::Varying));
+    assert_eq!(SqlType::from_u32(SQL_TYPE_NULL), Some(SqlType::Null));
+    assert_eq!(SqlType::from_u32(12345), None);
+    for sqltype in [
+        SQL_TYPE_TEXT,
+        SQL_TYPE_LONG,
+        SQL_TYPE_TIMESTAMP_TZ,
+        SQL_TYPE_DEC128,
+        SQL_TYPE_BOOLEAN,
+    ] {
+        assert_eq!(SqlType::from_u32(sqltype).unwrap().as_u32(), sqltype);
+    }
 }